@@ -0,0 +1,315 @@
+use crate::{
+    error, parse, MountEvent, MountInfo, MountInfoList, MountList, MountWatcher, SwapInfo, SwapList,
+};
+use futures_core::{ready, Stream};
+use std::{
+    io,
+    path::{Path, PathBuf},
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::{
+    fs::{self, File},
+    io::{unix::AsyncFd, AsyncBufRead, BufReader},
+};
+
+/// Asynchronously parse the `/proc/mounts` file, as a stream of mounts.
+pub struct AsyncMountIter<R> {
+    file:   R,
+    buffer: Vec<u8>,
+    line:   usize,
+    path:   Option<PathBuf>,
+}
+
+impl AsyncMountIter<BufReader<File>> {
+    pub async fn new() -> io::Result<Self> { Self::new_from_file("/proc/mounts").await }
+
+    /// Read mounts from any mount-tab-like file.
+    pub async fn new_from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let mut iter = Self::new_from_reader(BufReader::new(File::open(path).await?));
+        iter.path = Some(path.to_path_buf());
+        Ok(iter)
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncMountIter<R> {
+    /// Read mounts from any asynchronous reader.
+    pub fn new_from_reader(readable: R) -> Self {
+        Self { file: readable, buffer: Vec::with_capacity(512), line: 0, path: None }
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> Stream for AsyncMountIter<R> {
+    type Item = io::Result<MountInfo>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if !ready!(poll_read_line(&mut this.file, &mut this.buffer, cx))? {
+                return Poll::Ready(None);
+            }
+
+            this.line += 1;
+            let line = parse::trim_start(&this.buffer);
+            if !(line.starts_with(b"#") || line.is_empty()) {
+                let mount = MountInfo::from_bytes(&this.buffer)
+                    .map_err(|why| error::locate(why, Some(this.line), this.path.as_deref()));
+                this.buffer.clear();
+                return Poll::Ready(Some(mount));
+            }
+
+            this.buffer.clear();
+        }
+    }
+}
+
+/// Asynchronously parse the `/proc/swaps` file, as a stream of swaps.
+pub struct AsyncSwapIter<R> {
+    file:           R,
+    buffer:         Vec<u8>,
+    header_is_read: bool,
+    line:           usize,
+    path:           Option<PathBuf>,
+}
+
+impl AsyncSwapIter<BufReader<File>> {
+    pub async fn new() -> io::Result<Self> { Self::new_from_file("/proc/swaps").await }
+
+    pub async fn new_from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let mut iter = Self::new_from_reader(BufReader::new(File::open(path).await?));
+        iter.path = Some(path.to_path_buf());
+        Ok(iter)
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncSwapIter<R> {
+    /// Read swaps from any asynchronous reader, whose first line is a header.
+    pub fn new_from_reader(reader: R) -> Self {
+        Self {
+            file:           reader,
+            buffer:         Vec::with_capacity(512),
+            header_is_read: false,
+            line:           0,
+            path:           None,
+        }
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> Stream for AsyncSwapIter<R> {
+    type Item = io::Result<SwapInfo>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if !ready!(poll_read_line(&mut this.file, &mut this.buffer, cx))? {
+                return Poll::Ready(None);
+            }
+
+            this.line += 1;
+            if this.header_is_read {
+                let swap = SwapInfo::from_bytes(&this.buffer)
+                    .map_err(|why| error::locate(why, Some(this.line), this.path.as_deref()));
+                this.buffer.clear();
+                return Poll::Ready(Some(swap));
+            }
+
+            this.header_is_read = true;
+            this.buffer.clear();
+        }
+    }
+}
+
+/// Read a line into the buffer, including its newline.
+///
+/// The buffer is kept while the reader is pending, and must be cleared once the line has been
+/// used. Returns false if the end of the reader was reached without reading anything.
+fn poll_read_line<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    buffer: &mut Vec<u8>,
+    cx: &mut Context,
+) -> Poll<io::Result<bool>> {
+    loop {
+        let available = ready!(Pin::new(&mut *reader).poll_fill_buf(cx))?;
+        if available.is_empty() {
+            return Poll::Ready(Ok(!buffer.is_empty()));
+        }
+
+        let (is_complete, used) = match available.iter().position(|&byte| byte == b'\n') {
+            Some(newline) => (true, newline + 1),
+            None => (false, available.len()),
+        };
+
+        buffer.extend_from_slice(&available[..used]);
+        Pin::new(&mut *reader).consume(used);
+
+        if is_complete {
+            return Poll::Ready(Ok(true));
+        }
+    }
+}
+
+impl MountList {
+    /// Asynchronously read a new list of mounts into memory from `/proc/mounts`.
+    pub async fn new_async() -> io::Result<MountList> {
+        Self::new_from_file_async("/proc/mounts").await
+    }
+
+    /// Asynchronously read a new list of mounts into memory from any mount-tab-like file.
+    pub async fn new_from_file_async<P: AsRef<Path>>(path: P) -> io::Result<MountList> {
+        let path = path.as_ref();
+        Self::new_from_reader(&fs::read(path).await?[..])
+            .map_err(|why| error::locate(why, None, Some(path)))
+    }
+}
+
+impl MountInfoList {
+    /// Asynchronously read a new list of mounts into memory from `/proc/self/mountinfo`.
+    pub async fn new_async() -> io::Result<MountInfoList> {
+        Self::new_from_file_async("/proc/self/mountinfo").await
+    }
+
+    /// Asynchronously read a new list of mounts into memory from any mountinfo-like file.
+    pub async fn new_from_file_async<P: AsRef<Path>>(path: P) -> io::Result<MountInfoList> {
+        let path = path.as_ref();
+        Self::new_from_reader(&fs::read(path).await?[..])
+            .map_err(|why| error::locate(why, None, Some(path)))
+    }
+}
+
+impl SwapList {
+    /// Asynchronously read a new list of swaps into memory from `/proc/swaps`.
+    pub async fn new_async() -> io::Result<SwapList> {
+        Self::new_from_file_async("/proc/swaps").await
+    }
+
+    /// Asynchronously read a new list of swaps into memory from any swaps-like file.
+    pub async fn new_from_file_async<P: AsRef<Path>>(path: P) -> io::Result<SwapList> {
+        let path = path.as_ref();
+        Self::new_from_reader(&fs::read(path).await?[..])
+            .map_err(|why| error::locate(why, None, Some(path)))
+    }
+}
+
+/// A stream of the changes to the mount table of a process, as reported by a `MountWatcher`.
+///
+/// The watcher is registered with the reactor of the tokio runtime, and so must be created from
+/// within one.
+pub struct AsyncMountWatcher {
+    watcher: AsyncFd<MountWatcher>,
+}
+
+impl AsyncMountWatcher {
+    /// Watch the mounts of the current process.
+    pub fn new() -> io::Result<Self> { Self::from_watcher(MountWatcher::new()?) }
+
+    /// Watch the mounts of the process with the given PID.
+    pub fn new_from_pid(pid: u32) -> io::Result<Self> {
+        Self::from_watcher(MountWatcher::new_from_pid(pid)?)
+    }
+
+    /// Register an existing watcher with the tokio runtime.
+    pub fn from_watcher(watcher: MountWatcher) -> io::Result<Self> {
+        Ok(AsyncMountWatcher { watcher: AsyncFd::new(watcher)? })
+    }
+
+    /// The mounts as of the last time that the table was read.
+    pub fn mounts(&self) -> &MountInfoList { self.watcher.get_ref().mounts() }
+
+    /// Stop watching asynchronously, and return the blocking watcher.
+    pub fn into_inner(self) -> MountWatcher { self.watcher.into_inner() }
+}
+
+/// Yields the events of each change of the mount table.
+impl Stream for AsyncMountWatcher {
+    type Item = io::Result<Vec<MountEvent>>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            // The file is always readable, and the readiness is signaled again whenever the
+            // table changes. The table is read again each time, since checking `POLLPRI` here
+            // would race with the reactor, which consumes it.
+            ready!(this.watcher.poll_read_ready(cx))?.clear_ready();
+
+            match this.watcher.get_mut().refresh() {
+                Ok(ref events) if events.is_empty() => continue,
+                result => return Poll::Ready(Some(result)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::Future;
+
+    /// Resolves to the next item of a stream.
+    struct Next<'a, S>(&'a mut S);
+
+    impl<'a, S: Stream + Unpin> Future for Next<'a, S> {
+        type Output = Option<S::Item>;
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+            Pin::new(&mut *self.0).poll_next(cx)
+        }
+    }
+
+    #[test]
+    fn streams() {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        runtime.block_on(async {
+            let input = b"# comment\n/dev/sda1 / ext4 rw 0 0\n\ntmpfs /tmp tmpfs rw 0 0";
+            let mut mounts = AsyncMountIter::new_from_reader(&input[..]);
+            let mount = Next(&mut mounts).await.unwrap().unwrap();
+            assert_eq!(mount, "/dev/sda1 / ext4 rw 0 0".parse::<MountInfo>().unwrap());
+            assert_eq!(Next(&mut mounts).await.unwrap().unwrap().fstype, "tmpfs");
+            assert!(Next(&mut mounts).await.is_none());
+
+            let input = b"Filename Type Size Used Priority\n/dev/sda5 partition 8388600 0 -2\n";
+            let mut swaps = AsyncSwapIter::new_from_reader(&input[..]);
+            assert_eq!(Next(&mut swaps).await.unwrap().unwrap().size, 8_388_600);
+            assert!(Next(&mut swaps).await.is_none());
+
+            let mounts = MountList::new_async().await.unwrap();
+            assert_eq!(mounts, MountList::new().unwrap());
+        });
+    }
+
+    #[test]
+    fn watcher() {
+        // Mount within a private namespace of this thread, which leaves the host untouched, and
+        // which requires privileges.
+        if unsafe { libc::unshare(libc::CLONE_NEWNS) } != 0 {
+            return;
+        }
+
+        let private = "none / none rprivate 0 0".parse::<MountInfo>().unwrap();
+        private.mount().unwrap();
+
+        let dest = std::env::temp_dir().join(format!("proc-mounts-watch-{}", std::process::id()));
+        std::fs::create_dir_all(&dest).unwrap();
+        let mount = format!("tmpfs {} tmpfs rw 0 0", dest.display()).parse::<MountInfo>().unwrap();
+
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_io().build().unwrap();
+        runtime.block_on(async {
+            let watcher = MountWatcher::new_from_file("/proc/thread-self/mountinfo").unwrap();
+            let mut watcher = AsyncMountWatcher::from_watcher(watcher).unwrap();
+            assert!(!watcher.mounts().0.iter().any(|mounted| mounted.dest == dest));
+
+            mount.mount().unwrap();
+            let events = Next(&mut watcher).await.unwrap().unwrap();
+            assert!(matches!(events[..], [MountEvent::Added(ref added)] if added.dest == dest));
+
+            mount.unmount(crate::UnmountFlags::empty()).unwrap();
+            let events = Next(&mut watcher).await.unwrap().unwrap();
+            assert!(
+                matches!(events[..], [MountEvent::Removed(ref removed)] if removed.dest == dest)
+            );
+        });
+
+        std::fs::remove_dir(&dest).unwrap();
+    }
+}
@@ -0,0 +1,705 @@
+//! Lists mounts, fstab entries, and swaps, in the manner of `findmnt(8)`.
+
+use proc_mounts::{
+    Escaped, MountInfo, MountInfoList, MountList, MountOption, MountTab, MountTree, ParseError,
+    SwapInfo, SwapList,
+};
+use std::{
+    collections::HashMap,
+    env,
+    ffi::OsString,
+    fs,
+    io::{self, Error, Write},
+    path::{Path, PathBuf},
+    process,
+};
+
+const USAGE: &str = "\
+Usage: proc-mounts [options] [<source> | <mountpoint>]
+
+Lists the mounts of the current process, the entries of an fstab, or the active swaps.
+
+Tables:
+  -s, --fstab               list the entries of /etc/fstab
+  -F, --tab-file <path>     list the entries of another fstab
+      --swaps               list the active swaps
+
+Output:
+      --tree                display mounts as a tree (the default for mounts)
+  -l, --list                display a table (the default for fstab and swaps)
+  -J, --json                display the entries as JSON
+  -P, --pairs               display each entry as KEY=\"value\" pairs
+  -n, --noheadings          do not display a header line
+
+Filters:
+  -S, --source <spec>       a source, such as /dev/sda1, UUID=..., or LABEL=...
+  -M, --mountpoint <path>   a mount point
+  -T, --target <path>       the mount which contains a path
+  -t, --types <list>        comma-separated file system types, which are excluded if
+                            prefixed with `no`
+  -O, --options <list>      comma-separated options which must all be set
+
+  -h, --help                display this help
+  -V, --version             display the version
+
+The exit status is 1 if no entries match.
+";
+
+/// The options which are accepted, as their short name, long name, and whether they take a
+/// value.
+const OPTIONS: &[(Option<char>, &str, bool)] = &[
+    (Some('s'), "fstab", false),
+    (Some('F'), "tab-file", true),
+    (None, "swaps", false),
+    (None, "tree", false),
+    (Some('l'), "list", false),
+    (Some('J'), "json", false),
+    (Some('P'), "pairs", false),
+    (Some('n'), "noheadings", false),
+    (Some('S'), "source", true),
+    (Some('M'), "mountpoint", true),
+    (Some('T'), "target", true),
+    (Some('t'), "types", true),
+    (Some('O'), "options", true),
+    (Some('h'), "help", false),
+    (Some('V'), "version", false),
+];
+
+const MOUNT_HEADINGS: &[&str] = &["TARGET", "SOURCE", "FSTYPE", "OPTIONS"];
+
+const SWAP_HEADINGS: &[&str] = &["NAME", "TYPE", "SIZE", "USED", "PRIO"];
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Format {
+    Tree,
+    List,
+    Json,
+    Pairs,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Table {
+    Mounts,
+    Fstab(PathBuf),
+    Swaps,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+struct Filter {
+    /// Matches either the source or the mount point.
+    spec:       Option<OsString>,
+    source:     Option<OsString>,
+    mountpoint: Option<PathBuf>,
+    types:      Vec<String>,
+    options:    Vec<MountOption>,
+}
+
+impl Filter {
+    fn matches(&self, mount: &MountInfo) -> bool {
+        let source = |spec: &OsString| source_matches(mount, Path::new(spec));
+        let mountpoint = |path: &Path| mount.dest == path;
+
+        self.spec.iter().all(|spec| source(spec) || mountpoint(Path::new(spec)))
+            && self.source.iter().all(source)
+            && self.mountpoint.iter().all(|path| mountpoint(path))
+            && self.matches_type(&mount.fstype)
+            && self.options.iter().all(|option| mount.options.iter().any(|set| set == option))
+    }
+
+    fn matches_swap(&self, swap: &SwapInfo) -> bool {
+        let source = |spec: &OsString| swap.source == Path::new(spec);
+
+        self.spec.iter().all(source)
+            && self.source.iter().all(source)
+            && self.matches_type(&swap.kind.to_string_lossy())
+    }
+
+    /// Types prefixed with `no` are excluded, and if any others are given, only they are
+    /// included.
+    fn matches_type(&self, fstype: &str) -> bool {
+        let mut included = None;
+        for name in &self.types {
+            match name.strip_prefix("no") {
+                Some(excluded) if excluded == fstype => return false,
+                Some(_) => (),
+                None => included = Some(included == Some(true) || name == fstype),
+            }
+        }
+
+        included != Some(false)
+    }
+}
+
+/// Compares the source as it was specified, and also the device that it resolves to, so that
+/// a mount of `UUID=...` is found by the path of its device.
+fn source_matches(mount: &MountInfo, spec: &Path) -> bool {
+    mount.source == spec
+        || (mount.source.as_path().is_none()
+            && mount.source.is_device()
+            && mount.source.resolve().ok().as_deref() == Some(spec))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Args {
+    table:      Table,
+    format:     Option<Format>,
+    noheadings: bool,
+    filter:     Filter,
+    target:     Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Command {
+    Help,
+    Version,
+    List(Args),
+}
+
+fn parse_args<I: IntoIterator<Item = OsString>>(args: I) -> Result<Command, String> {
+    let mut parsed = Args {
+        table:      Table::Mounts,
+        format:     None,
+        noheadings: false,
+        filter:     Filter::default(),
+        target:     None,
+    };
+
+    let mut args = args.into_iter();
+    let mut positional = Vec::new();
+    while let Some(arg) = args.next() {
+        let text = match arg.to_str() {
+            Some(text) => text,
+            None => {
+                positional.push(arg);
+                continue;
+            }
+        };
+
+        // Each option is collected along with its value, if it takes one.
+        let mut options = Vec::new();
+        if text == "--" {
+            positional.extend(args.by_ref());
+        } else if let Some(long) = text.strip_prefix("--") {
+            let (name, value) = match long.split_once('=') {
+                Some((name, value)) => (name, Some(OsString::from(value))),
+                None => (long, None),
+            };
+
+            let &(_, name, takes_value) = OPTIONS
+                .iter()
+                .find(|option| option.1 == name)
+                .ok_or_else(|| format!("unrecognized option '--{}'", name))?;
+
+            let value = match (takes_value, value) {
+                (true, None) => Some(
+                    args.next().ok_or_else(|| format!("option '--{}' requires a value", name))?,
+                ),
+                (false, Some(_)) => {
+                    return Err(format!("option '--{}' does not take a value", name))
+                }
+                (_, value) => value,
+            };
+
+            options.push((name, value));
+        } else if text.len() > 1 && text.starts_with('-') {
+            for (index, short) in text.char_indices().skip(1) {
+                let &(_, name, takes_value) = OPTIONS
+                    .iter()
+                    .find(|option| option.0 == Some(short))
+                    .ok_or_else(|| format!("invalid option '-{}'", short))?;
+
+                if !takes_value {
+                    options.push((name, None));
+                    continue;
+                }
+
+                let rest = &text[index + short.len_utf8()..];
+                let value = if rest.is_empty() {
+                    args.next().ok_or_else(|| format!("option '-{}' requires a value", short))?
+                } else {
+                    OsString::from(rest)
+                };
+
+                options.push((name, Some(value)));
+                break;
+            }
+        } else {
+            positional.push(arg);
+        }
+
+        for (name, value) in options {
+            let utf8 = |value: OsString| {
+                value.into_string().map_err(|_| format!("the value of '--{}' is not UTF-8", name))
+            };
+
+            let filter = &mut parsed.filter;
+            match (name, value) {
+                ("help", _) => return Ok(Command::Help),
+                ("version", _) => return Ok(Command::Version),
+                ("fstab", _) => parsed.table = Table::Fstab("/etc/fstab".into()),
+                ("tab-file", Some(path)) => parsed.table = Table::Fstab(path.into()),
+                ("swaps", _) => parsed.table = Table::Swaps,
+                ("tree", _) => parsed.format = Some(Format::Tree),
+                ("list", _) => parsed.format = Some(Format::List),
+                ("json", _) => parsed.format = Some(Format::Json),
+                ("pairs", _) => parsed.format = Some(Format::Pairs),
+                ("noheadings", _) => parsed.noheadings = true,
+                ("source", Some(spec)) => filter.source = Some(spec),
+                ("mountpoint", Some(path)) => filter.mountpoint = Some(path.into()),
+                ("target", Some(path)) => parsed.target = Some(path.into()),
+                ("types", Some(types)) => {
+                    filter.types.extend(utf8(types)?.split(',').map(String::from));
+                }
+                ("options", Some(options)) => {
+                    let options = utf8(options)?;
+                    filter.options.extend(options.split(',').map(MountOption::from));
+                }
+                _ => unreachable!("option '--{}' was not handled", name),
+            }
+        }
+    }
+
+    if positional.len() > 1 {
+        return Err("only one source or mount point may be given".into());
+    }
+
+    parsed.filter.spec = positional.pop();
+
+    if parsed.table == Table::Swaps {
+        if parsed.format == Some(Format::Tree) {
+            return Err("swaps cannot be displayed as a tree".into());
+        }
+
+        let filter = &parsed.filter;
+        if filter.mountpoint.is_some() || !filter.options.is_empty() || parsed.target.is_some() {
+            return Err("swaps may only be filtered by their source and type".into());
+        }
+    }
+
+    Ok(Command::List(parsed))
+}
+
+fn main() {
+    let args = match parse_args(env::args_os().skip(1)) {
+        Ok(Command::List(args)) => args,
+        Ok(Command::Help) => {
+            print!("{}", USAGE);
+            return;
+        }
+        Ok(Command::Version) => {
+            println!("proc-mounts {}", env!("CARGO_PKG_VERSION"));
+            return;
+        }
+        Err(why) => {
+            eprintln!("proc-mounts: {}\nTry 'proc-mounts --help' for more information.", why);
+            process::exit(2);
+        }
+    };
+
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    match run(&mut stdout, &args).and_then(|found| stdout.flush().map(|_| found)) {
+        Ok(true) => (),
+        Ok(false) => process::exit(1),
+        Err(ref why) if why.kind() == io::ErrorKind::BrokenPipe => (),
+        Err(why) => {
+            eprintln!("proc-mounts: {}", why);
+            process::exit(1);
+        }
+    }
+}
+
+/// Writes the entries which match, and returns false if there were none.
+fn run<W: Write>(out: &mut W, args: &Args) -> io::Result<bool> {
+    let (mounts, parents) = match args.table {
+        Table::Mounts if args.format.unwrap_or(Format::Tree) == Format::Tree => {
+            mount_tree(&MountTree::new(MountInfoList::new()?))
+        }
+        Table::Mounts => parents_by_dest(MountList::new()?.0),
+        Table::Fstab(ref path) => {
+            let tab = fs::read_to_string(path).and_then(|tab| tab.parse::<MountTab>()).map_err(
+                |why| match ParseError::from_io(&why) {
+                    Some(details) => {
+                        ParseError { path: Some(path.clone()), ..details.clone() }.into()
+                    }
+                    None => Error::new(why.kind(), format!("{}: {}", path.display(), why)),
+                },
+            )?;
+
+            parents_by_dest(tab.iter_mounts().cloned().collect())
+        }
+        Table::Swaps => {
+            let swaps = SwapList::new()?;
+            let swaps = swaps.0.iter().filter(|swap| args.filter.matches_swap(swap));
+            return write_swaps(out, args, swaps.collect());
+        }
+    };
+
+    let mut kept: Vec<usize> =
+        (0..mounts.len()).filter(|&index| args.filter.matches(&mounts[index])).collect();
+    if let Some(ref target) = args.target {
+        let candidates = MountList(kept.iter().map(|&index| mounts[index].clone()).collect());
+        let found = candidates.get_mount_for_file(target)?;
+        let position = found
+            .and_then(|found| candidates.0.iter().position(|mount| std::ptr::eq(mount, found)));
+
+        kept = position.map(|position| kept[position]).into_iter().collect();
+    }
+
+    let (mounts, parents) = retain(mounts, &parents, &kept);
+    write_mounts(out, args, &mounts, &parents)
+}
+
+/// The mounts of the mount table in the order of a depth-first traversal, along with the index
+/// of the mount that each is mounted on, as given by the parent IDs of mountinfo.
+fn mount_tree(tree: &MountTree) -> (Vec<MountInfo>, Vec<Option<usize>>) {
+    let nodes: Vec<_> = tree.iter().collect();
+    let indices: HashMap<u32, usize> =
+        nodes.iter().enumerate().map(|(index, node)| (node.mount_id, index)).collect();
+
+    let parents = nodes
+        .iter()
+        .map(|node| node.parent().and_then(|parent| indices.get(&parent.mount_id).copied()))
+        .collect();
+
+    (nodes.into_iter().map(|node| MountInfo::from(node.entry().clone())).collect(), parents)
+}
+
+/// The mounts, along with the index of the mount that each is beneath, for tables such as an
+/// fstab which do not record their parents.
+///
+/// The parent of a mount is the last mount before it whose mount point contains its own.
+fn parents_by_dest(mounts: Vec<MountInfo>) -> (Vec<MountInfo>, Vec<Option<usize>>) {
+    let parents = mounts
+        .iter()
+        .enumerate()
+        .map(|(index, mount)| {
+            mounts[..index].iter().rposition(|parent| mount.dest.starts_with(&parent.dest))
+        })
+        .collect();
+
+    (mounts, parents)
+}
+
+/// Keeps the mounts at the given indices, which are in ascending order, and attaches each to the
+/// nearest of its ancestors which was also kept.
+fn retain(
+    mounts: Vec<MountInfo>,
+    parents: &[Option<usize>],
+    kept: &[usize],
+) -> (Vec<MountInfo>, Vec<Option<usize>>) {
+    let mut positions = vec![None; mounts.len()];
+    for (position, &index) in kept.iter().enumerate() {
+        positions[index] = Some(position);
+    }
+
+    let kept_parents = kept
+        .iter()
+        .map(|&index| {
+            // Bounded by the number of mounts, in case the parents contain a cycle.
+            let mut parent = parents[index];
+            for _ in 0..mounts.len() {
+                match parent {
+                    Some(ancestor) if positions[ancestor].is_none() => parent = parents[ancestor],
+                    _ => break,
+                }
+            }
+
+            parent.and_then(|parent| positions[parent])
+        })
+        .collect();
+
+    let mounts = mounts
+        .into_iter()
+        .zip(positions)
+        .filter_map(|(mount, position)| position.map(|_| mount))
+        .collect();
+
+    (mounts, kept_parents)
+}
+
+fn write_mounts<W: Write>(
+    out: &mut W,
+    args: &Args,
+    mounts: &[MountInfo],
+    parents: &[Option<usize>],
+) -> io::Result<bool> {
+    if mounts.is_empty() {
+        return Ok(false);
+    }
+
+    let columns = |mount: &MountInfo, target: String| {
+        vec![target, mount.source.to_string(), mount.fstype.clone(), mount.options.to_string()]
+    };
+
+    let target = |mount: &MountInfo| Escaped(mount.dest.as_os_str()).to_string();
+
+    let default = if args.table == Table::Mounts { Format::Tree } else { Format::List };
+    match args.format.unwrap_or(default) {
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut *out, mounts)?;
+            writeln!(out)?;
+        }
+        Format::Pairs => {
+            for mount in mounts {
+                write_pairs(out, MOUNT_HEADINGS, &columns(mount, target(mount)))?;
+            }
+        }
+        Format::List => {
+            let rows = mounts.iter().map(|mount| columns(mount, target(mount))).collect();
+            write_table(out, MOUNT_HEADINGS, rows, args.noheadings)?;
+        }
+        Format::Tree => {
+            let rows = tree(mounts, parents)
+                .into_iter()
+                .map(|(prefix, mount)| columns(mount, prefix + &target(mount)))
+                .collect();
+            write_table(out, MOUNT_HEADINGS, rows, args.noheadings)?;
+        }
+    }
+
+    Ok(true)
+}
+
+fn write_swaps<W: Write>(out: &mut W, args: &Args, swaps: Vec<&SwapInfo>) -> io::Result<bool> {
+    if swaps.is_empty() {
+        return Ok(false);
+    }
+
+    let columns = |swap: &SwapInfo| {
+        vec![
+            Escaped(swap.source.as_os_str()).to_string(),
+            Escaped(&swap.kind).to_string(),
+            swap.size.to_string(),
+            swap.used.to_string(),
+            swap.priority.to_string(),
+        ]
+    };
+
+    match args.format.unwrap_or(Format::List) {
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut *out, &swaps)?;
+            writeln!(out)?;
+        }
+        Format::Pairs => {
+            for swap in swaps {
+                write_pairs(out, SWAP_HEADINGS, &columns(swap))?;
+            }
+        }
+        Format::List | Format::Tree => {
+            let rows = swaps.into_iter().map(columns).collect();
+            write_table(out, SWAP_HEADINGS, rows, args.noheadings)?;
+        }
+    }
+
+    Ok(true)
+}
+
+/// Orders the mounts so that each follows the mount that it is beneath, given by the index of
+/// its parent, along with the lines which connect them.
+fn tree<'a>(mounts: &'a [MountInfo], parents: &[Option<usize>]) -> Vec<(String, &'a MountInfo)> {
+    let mut roots = Vec::new();
+    let mut children = vec![Vec::new(); mounts.len()];
+    for (index, parent) in parents.iter().enumerate() {
+        match *parent {
+            Some(parent) => children[parent].push(index),
+            None => roots.push(index),
+        }
+    }
+
+    fn visit<'a>(
+        mounts: &'a [MountInfo],
+        children: &[Vec<usize>],
+        index: usize,
+        indent: &str,
+        branch: &str,
+        rows: &mut Vec<(String, &'a MountInfo)>,
+    ) {
+        rows.push((format!("{}{}", indent, branch), &mounts[index]));
+
+        let indent = match branch {
+            "" => String::new(),
+            "└─" => format!("{}  ", indent),
+            _ => format!("{}│ ", indent),
+        };
+
+        let count = children[index].len();
+        for (position, &child) in children[index].iter().enumerate() {
+            let branch = if position + 1 == count { "└─" } else { "├─" };
+            visit(mounts, children, child, &indent, branch, rows);
+        }
+    }
+
+    let mut rows = Vec::with_capacity(mounts.len());
+    for root in roots {
+        visit(mounts, &children, root, "", "", &mut rows);
+    }
+
+    rows
+}
+
+/// Writes the rows with their columns aligned, as `findmnt --list` does.
+fn write_table<W: Write>(
+    out: &mut W,
+    headings: &[&str],
+    rows: Vec<Vec<String>>,
+    noheadings: bool,
+) -> io::Result<()> {
+    let headings: Vec<String> = headings.iter().map(|heading| heading.to_string()).collect();
+    let rows = if noheadings { rows } else { Some(headings).into_iter().chain(rows).collect() };
+
+    let mut widths = vec![0; rows.first().map_or(0, Vec::len)];
+    for row in &rows {
+        for (width, column) in widths.iter_mut().zip(row) {
+            *width = (*width).max(column.chars().count());
+        }
+    }
+
+    for row in &rows {
+        let last = row.len() - 1;
+        for (index, (column, &width)) in row.iter().zip(&widths).enumerate() {
+            if index == last {
+                writeln!(out, "{}", column)?;
+            } else {
+                write!(out, "{}{:pad$} ", column, "", pad = width - column.chars().count())?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes a row as `KEY="value"` pairs, escaping quotes in the same manner as whitespace.
+fn write_pairs<W: Write>(out: &mut W, headings: &[&str], row: &[String]) -> io::Result<()> {
+    for (index, (heading, column)) in headings.iter().zip(row).enumerate() {
+        let separator = if index == 0 { "" } else { " " };
+        write!(out, "{}{}=\"{}\"", separator, heading, column.replace('"', "\\042"))?;
+    }
+
+    writeln!(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Command, String> {
+        parse_args(args.iter().map(OsString::from))
+    }
+
+    fn mounts(lines: &[&str]) -> Vec<MountInfo> {
+        lines.iter().map(|line| line.parse::<MountInfo>().unwrap()).collect()
+    }
+
+    #[test]
+    fn args() {
+        let args = match parse(&["-lnt", "ext4,nonfs", "--options=ro", "-T/home", "/dev/sda1"]) {
+            Ok(Command::List(args)) => args,
+            other => panic!("unexpected result: {:?}", other),
+        };
+
+        assert_eq!(args.format, Some(Format::List));
+        assert!(args.noheadings);
+        assert_eq!(args.filter.types, vec!["ext4", "nonfs"]);
+        assert_eq!(args.filter.options, vec![MountOption::from("ro")]);
+        assert_eq!(args.filter.spec, Some(OsString::from("/dev/sda1")));
+        assert_eq!(args.target, Some(PathBuf::from("/home")));
+
+        assert_eq!(parse(&["-lh"]), Ok(Command::Help));
+        assert!(parse(&["--source"]).is_err());
+        assert!(parse(&["--json=yes"]).is_err());
+        assert!(parse(&["-x"]).is_err());
+        assert!(parse(&["/", "/home"]).is_err());
+        assert!(parse(&["--swaps", "--tree"]).is_err());
+        assert!(parse(&["--swaps", "-T", "/"]).is_err());
+    }
+
+    #[test]
+    fn filter() {
+        let mounts = mounts(&[
+            "/dev/sda1 / ext4 rw,noatime 0 1",
+            "UUID=abcd /home ext4 ro 0 2",
+            "server:/export /srv nfs rw 0 0",
+        ]);
+
+        let filter = Filter { types: vec!["nonfs".into()], ..Filter::default() };
+        assert_eq!(mounts.iter().filter(|mount| filter.matches(mount)).count(), 2);
+
+        let filter = Filter { types: vec!["ext4".into(), "nfs".into()], ..Filter::default() };
+        assert_eq!(mounts.iter().filter(|mount| filter.matches(mount)).count(), 3);
+
+        let filter = Filter { options: vec!["ro".into()], ..Filter::default() };
+        assert_eq!(mounts.iter().filter(|mount| filter.matches(mount)).count(), 1);
+
+        let filter = Filter { spec: Some("/home".into()), ..Filter::default() };
+        assert!(filter.matches(&mounts[1]) && !filter.matches(&mounts[0]));
+
+        let filter = Filter { source: Some("UUID=abcd".into()), ..Filter::default() };
+        assert!(filter.matches(&mounts[1]) && !filter.matches(&mounts[2]));
+    }
+
+    #[test]
+    fn output() {
+        let mounts = mounts(&[
+            "/dev/sda1 / ext4 rw 0 0",
+            "proc /proc proc rw 0 0",
+            "/dev/sda2 /home ext4 rw 0 0",
+            "/dev/sda3 /home/my\\040data ext4 rw 0 0",
+        ]);
+
+        let args = match parse(&[]) {
+            Ok(Command::List(args)) => args,
+            other => panic!("unexpected result: {:?}", other),
+        };
+
+        let (mounts, parents) = parents_by_dest(mounts);
+        let mut out = Vec::new();
+        assert!(write_mounts(&mut out, &args, &mounts, &parents).unwrap());
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+TARGET               SOURCE    FSTYPE OPTIONS
+/                    /dev/sda1 ext4   rw
+├─/proc              proc      proc   rw
+└─/home              /dev/sda2 ext4   rw
+  └─/home/my\\040data /dev/sda3 ext4   rw
+"
+        );
+
+        assert!(!write_mounts(&mut Vec::new(), &args, &[], &[]).unwrap());
+
+        let mut out = Vec::new();
+        write_pairs(&mut out, MOUNT_HEADINGS, &["/a\"b".into(), "none".into()]).unwrap();
+        assert_eq!(out, b"TARGET=\"/a\\042b\" SOURCE=\"none\"\n");
+    }
+
+    #[test]
+    fn mount_tree_parents() {
+        // `/mnt/sub` was mounted on the root file system before `/mnt` was mounted over it.
+        let list = MountInfoList::new_from_reader(
+            &b"\
+1 0 8:1 / / rw - ext4 /dev/sda1 rw
+3 1 8:3 / /mnt/sub rw - ext4 /dev/sda3 rw
+2 1 8:2 / /mnt rw - ext4 /dev/sda2 rw
+4 2 0:4 / /mnt/tmp rw - tmpfs tmpfs rw
+"[..],
+        )
+        .unwrap();
+
+        let (mounts, parents) = mount_tree(&MountTree::new(list));
+        let rows = tree(&mounts, &parents);
+        let targets: Vec<_> = rows
+            .iter()
+            .map(|(prefix, mount)| format!("{}{}", prefix, mount.dest.display()))
+            .collect();
+        assert_eq!(targets, vec!["/", "├─/mnt/sub", "└─/mnt", "  └─/mnt/tmp"]);
+
+        // Mounts whose parent is filtered out are attached to the nearest ancestor which is kept.
+        let (mounts, parents) = retain(mounts, &parents, &[0, 3]);
+        assert_eq!(parents, vec![None, Some(0)]);
+        assert_eq!(mounts[1].dest, Path::new("/mnt/tmp"));
+
+        let (mounts, parents) = retain(mounts, &parents, &[1]);
+        assert_eq!((mounts.len(), parents), (1, vec![None]));
+    }
+}
@@ -0,0 +1,168 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+/// Describes a line of a mount table, mountinfo, swaps, or similar file which could not be
+/// parsed, and where it was read from.
+///
+/// Parse failures are still returned as an `io::Error` of the `InvalidData` kind, which wraps
+/// this type. Use `ParseError::from_io` to inspect the details of such an error.
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub struct ParseError {
+    /// What was wrong with the line.
+    pub message: String,
+    /// The name of the field which could not be parsed, such as `dest` or `pass`.
+    pub field:   Option<&'static str>,
+    /// The column, counted in bytes from 1, where the offending field starts.
+    pub column:  Option<usize>,
+    /// The text of the line, without its newline. Bytes which are not UTF-8 are replaced.
+    pub text:    Option<String>,
+    /// The number of the line, counted from 1, if it was read from a file or reader.
+    pub line:    Option<usize>,
+    /// The file that the line was read from, if it was opened by path.
+    pub path:    Option<PathBuf>,
+}
+
+impl ParseError {
+    /// An error which has not yet been attributed to a line.
+    pub fn new<M: Into<String>>(message: M) -> Self {
+        ParseError {
+            message: message.into(),
+            field:   None,
+            column:  None,
+            text:    None,
+            line:    None,
+            path:    None,
+        }
+    }
+
+    /// The details of an error returned by this crate, if it is a parse failure.
+    pub fn from_io(error: &io::Error) -> Option<&ParseError> {
+        error.get_ref()?.downcast_ref::<ParseError>()
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        if let Some(ref path) = self.path {
+            write!(fmt, "{}: ", path.display())?;
+        }
+
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => write!(fmt, "line {}, column {}: ", line, column)?,
+            (Some(line), None) => write!(fmt, "line {}: ", line)?,
+            (None, Some(column)) => write!(fmt, "column {}: ", column)?,
+            (None, None) => (),
+        }
+
+        fmt.write_str(&self.message)
+    }
+}
+
+impl Error for ParseError {}
+
+impl From<ParseError> for io::Error {
+    fn from(error: ParseError) -> Self { io::Error::new(ErrorKind::InvalidData, error) }
+}
+
+/// Records the number of the line that a parse failure was found on, and the file that it was
+/// read from. Any other error is returned as it is.
+pub(crate) fn locate(mut error: io::Error, line: Option<usize>, path: Option<&Path>) -> io::Error {
+    let details = error.get_mut().and_then(|inner| inner.downcast_mut::<ParseError>());
+    if let Some(details) = details {
+        if line.is_some() {
+            details.line = line;
+        }
+
+        if let Some(path) = path {
+            details.path = Some(path.to_path_buf());
+        }
+    }
+
+    error
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MountInfoEntry, MountList, MountStatsList, MountTab, SwapList};
+
+    fn details(error: io::Error) -> ParseError {
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        ParseError::from_io(&error).unwrap().clone()
+    }
+
+    #[test]
+    fn mounts() {
+        let input = "tmpfs /tmp tmpfs rw 0 0\n/dev/sda1 / ext4 rw x 1\n";
+        let error = details(MountList::new_from_reader(input.as_bytes()).unwrap_err());
+        assert_eq!(error.message, "dump value is not a number");
+        assert_eq!(error.field, Some("dump"));
+        assert_eq!(error.column, Some(21));
+        assert_eq!(error.text.as_deref(), Some("/dev/sda1 / ext4 rw x 1"));
+        assert_eq!(error.line, Some(2));
+        assert_eq!(error.to_string(), "line 2, column 21: dump value is not a number");
+
+        let error = details(MountList::parse_from("/dev/sda1 /".lines()).unwrap_err());
+        assert_eq!(error.to_string(), "line 1: missing type");
+        assert_eq!(error.field, Some("type"));
+        assert_eq!(error.column, None);
+
+        let input = "# comment\n\n  /dev/sda1 /mnt/bad\\09 ext4 defaults\n";
+        let error = details(input.parse::<MountTab>().unwrap_err());
+        assert_eq!((error.line, error.column, error.field), (Some(3), Some(13), Some("dest")));
+        assert_eq!(error.text.as_deref(), Some("  /dev/sda1 /mnt/bad\\09 ext4 defaults"));
+
+        let error = details(MountTab::from_str_lossless(input).unwrap_err());
+        assert_eq!((error.line, error.column), (Some(3), Some(13)));
+
+        let error = ParseError { path: Some("/etc/fstab".into()), ..error };
+        assert_eq!(
+            io::Error::from(error).to_string(),
+            "/etc/fstab: line 3, column 13: invalid digit found in string"
+        );
+    }
+
+    #[test]
+    fn mountinfo() {
+        let line = "36 35 98:0 /mnt1 /mnt2 rw shared:x - ext3 /dev/root rw";
+        let error = details(line.parse::<MountInfoEntry>().unwrap_err());
+        assert_eq!(error.field, Some("optional field"));
+        assert_eq!(error.column, Some(27));
+        assert_eq!(error.message, "peer group ID is not a number");
+        assert_eq!(error.line, None);
+
+        let error =
+            details("36 35 98x0 / / rw - ext3 /dev/root rw".parse::<MountInfoEntry>().unwrap_err());
+        assert_eq!((error.field, error.column), (Some("major:minor"), Some(7)));
+
+        let error =
+            details("36 35 98:y / / rw - ext3 /dev/root rw".parse::<MountInfoEntry>().unwrap_err());
+        assert_eq!((error.field, error.column), (Some("minor ID"), Some(10)));
+    }
+
+    #[test]
+    fn swaps_and_stats() {
+        let input =
+            "Filename Type Size Used Priority\n/dev/sda5 partition 1 0 -2\n/swap file big 0 -3\n";
+        let error = details(SwapList::new_from_reader(input.as_bytes()).unwrap_err());
+        assert_eq!((error.line, error.column, error.field), (Some(3), Some(12), Some("size")));
+
+        let input = "device tmpfs mounted on /tmp with fstype tmpfs\ndevice nfs mounted on /mnt \
+                     with fstype nfs\n\tage:\tsoon\n";
+        let error = details(MountStatsList::new_from_reader(input.as_bytes()).unwrap_err());
+        assert_eq!((error.line, error.column, error.field), (Some(3), Some(7), Some("age")));
+        assert_eq!(error.text.as_deref(), Some("\tage:\tsoon"));
+
+        let input = "device tmpfs mounted on /tmp with fstype tmpfs extra\n";
+        let error = details(MountStatsList::new_from_reader(input.as_bytes()).unwrap_err());
+        assert_eq!((error.line, error.column, error.field), (Some(1), Some(48), Some("statvers")));
+
+        let error = io::Error::new(ErrorKind::InvalidData, "not a parse error");
+        assert!(ParseError::from_io(&error).is_none());
+        assert!(ParseError::from_io(&locate(error, Some(1), None)).is_none());
+    }
+}
@@ -0,0 +1,118 @@
+use std::{
+    char,
+    ffi::{OsStr, OsString},
+    fmt::{self, Display, Formatter, Write},
+    io::{self, Error, ErrorKind},
+    os::unix::ffi::{OsStrExt, OsStringExt},
+    str,
+};
+
+/// Returns true for the bytes which would otherwise be mistaken for field separators.
+fn is_special(byte: u8) -> bool { matches!(byte, b' ' | b'\t' | b'\n' | b'\\') }
+
+/// Decode the `\NNN` octal escapes within a field.
+pub(crate) fn decode(value: &[u8]) -> io::Result<OsString> {
+    let mut ret = Vec::new();
+
+    let mut bytes = value.iter().copied();
+    while let Some(b) = bytes.next() {
+        match b {
+            b'\\' => {
+                let mut code = 0;
+                for _i in 0..3 {
+                    if let Some(b) = bytes.next() {
+                        code *= 8;
+                        code += u32::from_str_radix(&(b as char).to_string(), 8)
+                            .map_err(|err| Error::new(ErrorKind::Other, err))?;
+                    } else {
+                        return Err(Error::new(ErrorKind::Other, "truncated octal code"));
+                    }
+                }
+                ret.push(code as u8);
+            }
+            _ => {
+                ret.push(b);
+            }
+        }
+    }
+
+    Ok(OsString::from_vec(ret))
+}
+
+/// Encode a field into `out` with `\NNN` octal escapes for spaces, tabs, newlines, and
+/// backslashes. All other bytes are written as they are, including those which are not UTF-8.
+pub(crate) fn encode(value: &OsStr, out: &mut Vec<u8>) {
+    for &byte in value.as_bytes() {
+        if is_special(byte) {
+            out.extend_from_slice(format!("\\{:03o}", byte).as_bytes());
+        } else {
+            out.push(byte);
+        }
+    }
+}
+
+/// Displays a field with `\NNN` octal escapes for spaces, tabs, newlines, and backslashes.
+///
+/// Bytes which are not valid UTF-8 are escaped too, so that the field can be recovered exactly
+/// by decoding the displayed text. This is how paths are written by the `Display`
+/// implementations of this crate.
+#[derive(Debug, Copy, Clone)]
+pub struct Escaped<'a>(pub &'a OsStr);
+
+impl<'a> Display for Escaped<'a> {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        let mut bytes = self.0.as_bytes();
+
+        while !bytes.is_empty() {
+            let (valid, invalid) = match str::from_utf8(bytes) {
+                Ok(valid) => (valid, &[][..]),
+                Err(why) => {
+                    let (valid, rest) = bytes.split_at(why.valid_up_to());
+                    let invalid = why.error_len().unwrap_or(rest.len());
+                    // The prefix was validated by `from_utf8`.
+                    (str::from_utf8(valid).unwrap_or_default(), &rest[..invalid])
+                }
+            };
+
+            for c in valid.chars() {
+                if c.is_ascii() && is_special(c as u8) {
+                    write!(fmt, "\\{:03o}", c as u32)?;
+                } else {
+                    fmt.write_char(c)?;
+                }
+            }
+
+            for byte in invalid {
+                write!(fmt, "\\{:03o}", byte)?;
+            }
+
+            bytes = &bytes[valid.len() + invalid.len()..];
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let value = OsStr::new("a b\tc\nd\\e");
+        let escaped = Escaped(value).to_string();
+        assert_eq!(escaped, "a\\040b\\011c\\012d\\134e");
+        assert_eq!(decode(escaped.as_bytes()).unwrap(), value);
+        assert!(decode(b"a\\04").is_err());
+
+        let value = OsStr::from_bytes(b"caf\xc3\xa9 \xff\xfe!");
+        let escaped = Escaped(value).to_string();
+        assert_eq!(escaped, "caf\u{e9}\\040\\377\\376!");
+        assert_eq!(decode(escaped.as_bytes()).unwrap(), value);
+
+        let mut encoded = Vec::new();
+        encode(value, &mut encoded);
+        assert_eq!(encoded, b"caf\xc3\xa9\\040\xff\xfe!");
+        assert_eq!(decode(&encoded).unwrap(), value);
+    }
+}
@@ -0,0 +1,480 @@
+use crate::{error, parse, MountInfo, MountList, MountSource};
+use std::{
+    fmt::{self, Display, Formatter},
+    fs::{self, File},
+    io::{self, BufRead, BufReader},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// The kind of storage which backs a file system.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub enum FilesystemClass {
+    /// A file system which is provided by the kernel, such as `proc`, `sysfs`, or `tmpfs`.
+    Virtual,
+    /// A file system which is stored on a block device, such as `ext4` or `vfat`.
+    Block,
+    /// A file system which is accessed over the network, such as `nfs`, `cifs`, or `sshfs`.
+    Network,
+    /// A file system which is provided by a FUSE process, and which is not otherwise known.
+    Fuse,
+    /// A file system which merges other file systems, such as `overlay`.
+    Overlay,
+}
+
+/// File system types whose class is known in advance.
+const KNOWN: &[(&str, FilesystemClass)] = &[
+    ("autofs", FilesystemClass::Virtual),
+    ("binfmt_misc", FilesystemClass::Virtual),
+    ("bpf", FilesystemClass::Virtual),
+    ("cgroup", FilesystemClass::Virtual),
+    ("cgroup2", FilesystemClass::Virtual),
+    ("configfs", FilesystemClass::Virtual),
+    ("cpuset", FilesystemClass::Virtual),
+    ("debugfs", FilesystemClass::Virtual),
+    ("devpts", FilesystemClass::Virtual),
+    ("devtmpfs", FilesystemClass::Virtual),
+    ("efivarfs", FilesystemClass::Virtual),
+    ("fusectl", FilesystemClass::Virtual),
+    ("hugetlbfs", FilesystemClass::Virtual),
+    ("mqueue", FilesystemClass::Virtual),
+    ("nsfs", FilesystemClass::Virtual),
+    ("pipefs", FilesystemClass::Virtual),
+    ("proc", FilesystemClass::Virtual),
+    ("pstore", FilesystemClass::Virtual),
+    ("ramfs", FilesystemClass::Virtual),
+    ("rpc_pipefs", FilesystemClass::Virtual),
+    ("securityfs", FilesystemClass::Virtual),
+    ("selinuxfs", FilesystemClass::Virtual),
+    ("sockfs", FilesystemClass::Virtual),
+    ("sysfs", FilesystemClass::Virtual),
+    ("tmpfs", FilesystemClass::Virtual),
+    ("tracefs", FilesystemClass::Virtual),
+    ("9p", FilesystemClass::Network),
+    ("afs", FilesystemClass::Network),
+    ("ceph", FilesystemClass::Network),
+    ("cifs", FilesystemClass::Network),
+    ("coda", FilesystemClass::Network),
+    ("davfs", FilesystemClass::Network),
+    ("glusterfs", FilesystemClass::Network),
+    ("lustre", FilesystemClass::Network),
+    ("ncpfs", FilesystemClass::Network),
+    ("nfs", FilesystemClass::Network),
+    ("nfs4", FilesystemClass::Network),
+    ("smb3", FilesystemClass::Network),
+    ("smbfs", FilesystemClass::Network),
+    ("vboxsf", FilesystemClass::Network),
+    ("virtiofs", FilesystemClass::Network),
+    ("fuse.ceph-fuse", FilesystemClass::Network),
+    ("fuse.curlftpfs", FilesystemClass::Network),
+    ("fuse.gcsfuse", FilesystemClass::Network),
+    ("fuse.glusterfs", FilesystemClass::Network),
+    ("fuse.rclone", FilesystemClass::Network),
+    ("fuse.s3fs", FilesystemClass::Network),
+    ("fuse.sshfs", FilesystemClass::Network),
+    ("aufs", FilesystemClass::Overlay),
+    ("overlay", FilesystemClass::Overlay),
+    ("overlayfs", FilesystemClass::Overlay),
+    ("unionfs", FilesystemClass::Overlay),
+    ("fuse.fuse-overlayfs", FilesystemClass::Overlay),
+    ("fuse.mergerfs", FilesystemClass::Overlay),
+    ("fuse.unionfs", FilesystemClass::Overlay),
+    ("fuse.unionfs-fuse", FilesystemClass::Overlay),
+    ("fuseblk", FilesystemClass::Block),
+    ("zfs", FilesystemClass::Block),
+];
+
+impl FilesystemClass {
+    /// The class of a file system type which is known in advance, if any.
+    ///
+    /// Any `fuse.<subtype>` which is not known is classified as `Fuse`.
+    pub fn from_known(fstype: &str) -> Option<Self> {
+        KNOWN.iter().find(|(known, _)| *known == fstype).map(|&(_, class)| class).or_else(|| {
+            if fstype == "fuse" || fstype.starts_with("fuse.") {
+                Some(FilesystemClass::Fuse)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Classify a file system type, using whether the kernel marks it as `nodev` in
+    /// `/proc/filesystems` when it is not known in advance.
+    pub fn new(fstype: &str, nodev: bool) -> Self {
+        Self::from_known(fstype).unwrap_or(if nodev {
+            FilesystemClass::Virtual
+        } else {
+            FilesystemClass::Block
+        })
+    }
+
+    /// Returns true if the file system holds data which is stored somewhere, rather than being
+    /// provided by the kernel.
+    pub fn is_real(self) -> bool { self != FilesystemClass::Virtual }
+
+    /// The name of the class, in lowercase.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FilesystemClass::Virtual => "virtual",
+            FilesystemClass::Block => "block",
+            FilesystemClass::Network => "network",
+            FilesystemClass::Fuse => "fuse",
+            FilesystemClass::Overlay => "overlay",
+        }
+    }
+}
+
+impl Display for FilesystemClass {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result { fmt.write_str(self.as_str()) }
+}
+
+impl MountInfo {
+    /// Classify the file system of this mount.
+    ///
+    /// Types which are not known in advance are looked up in `/proc/filesystems`. If that cannot
+    /// be read, mounts of pseudo sources are assumed to be virtual.
+    pub fn class(&self) -> FilesystemClass { self.class_with(FilesystemList::new().ok().as_ref()) }
+
+    pub(crate) fn class_with(&self, filesystems: Option<&FilesystemList>) -> FilesystemClass {
+        let nodev = filesystems.and_then(|filesystems| filesystems.get(&self.fstype));
+        let nodev = match nodev {
+            Some(info) => info.nodev,
+            None => matches!(self.source, MountSource::Pseudo(_)),
+        };
+
+        FilesystemClass::new(&self.fstype, nodev)
+    }
+}
+
+impl MountList {
+    /// Classify the file system of each mount, reading `/proc/filesystems` only once.
+    pub fn classify(&self) -> Vec<(&MountInfo, FilesystemClass)> {
+        let filesystems = FilesystemList::new().ok();
+        self.0.iter().map(|mount| (mount, mount.class_with(filesystems.as_ref()))).collect()
+    }
+
+    /// The mounts whose file systems are of the given class.
+    pub fn filter_class(&self, class: FilesystemClass) -> impl Iterator<Item = &MountInfo> + '_ {
+        self.classify()
+            .into_iter()
+            .filter(move |&(_, other)| other == class)
+            .map(|(mount, _)| mount)
+    }
+}
+
+/// A file system type which is registered with the kernel.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Default, Clone, Hash, Eq, PartialEq)]
+pub struct FilesystemInfo {
+    /// The name of the type, as passed to `mount(2)`.
+    pub name:  String,
+    /// Defines if the file system does not need a block device.
+    pub nodev: bool,
+}
+
+impl Display for FilesystemInfo {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        write!(fmt, "{}\t{}", if self.nodev { "nodev" } else { "" }, self.name)
+    }
+}
+
+impl FromStr for FilesystemInfo {
+    type Err = io::Error;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> { Self::from_bytes(line.as_bytes()) }
+}
+
+impl FilesystemInfo {
+    /// Parse a `/proc/filesystems`-like line.
+    pub fn from_bytes(line: &[u8]) -> io::Result<Self> {
+        let line = parse::Line(line);
+        let mut parts = line.fields();
+
+        let (nodev, name) = match (parts.next(), parts.next()) {
+            (Some((_, b"nodev")), Some(name)) => (true, name),
+            (Some((_, b"nodev")), None) | (None, _) => return Err(line.missing("type")),
+            (Some(name), None) => (false, name),
+            (Some((offset, _)), Some(_)) => {
+                return Err(line.field_error(offset, "nodev", "expected nodev before the type"))
+            }
+        };
+
+        if let Some((offset, _)) = parts.next() {
+            return Err(line.field_error(offset, "type", "unexpected field after the type"));
+        }
+
+        let name = line.utf8(name, "type", "type is not UTF-8")?.to_owned();
+        Ok(FilesystemInfo { name, nodev })
+    }
+
+    /// Returns true if the file system must be mounted from a block device.
+    pub fn requires_block_device(&self) -> bool { !self.nodev }
+}
+
+/// A list of parsed file system types from `/proc/filesystems`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Default, Clone, Hash, Eq, PartialEq)]
+pub struct FilesystemList(pub Vec<FilesystemInfo>);
+
+impl FilesystemList {
+    pub fn parse_from<'a, I: Iterator<Item = &'a str>>(lines: I) -> io::Result<FilesystemList> {
+        parse::lines(lines, FilesystemInfo::from_bytes).map(FilesystemList)
+    }
+
+    /// Read the file system types which are supported by the running kernel.
+    pub fn new() -> io::Result<FilesystemList> {
+        Ok(FilesystemList(FilesystemIter::new()?.collect::<io::Result<Vec<FilesystemInfo>>>()?))
+    }
+
+    pub fn new_from_file<P: AsRef<Path>>(path: P) -> io::Result<FilesystemList> {
+        Ok(FilesystemList(
+            FilesystemIter::new_from_file(path)?.collect::<io::Result<Vec<FilesystemInfo>>>()?,
+        ))
+    }
+
+    pub fn new_from_reader<R: BufRead>(reader: R) -> io::Result<FilesystemList> {
+        Ok(FilesystemList(
+            FilesystemIter::new_from_reader(reader).collect::<io::Result<Vec<FilesystemInfo>>>()?,
+        ))
+    }
+
+    /// Find a file system type by its name.
+    ///
+    /// A FUSE subtype, such as `fuse.sshfs`, is found by its main type.
+    pub fn get(&self, fstype: &str) -> Option<&FilesystemInfo> {
+        let fstype = fstype.split('.').next().unwrap_or(fstype);
+        self.0.iter().find(|info| info.name == fstype)
+    }
+
+    /// Returns true if the kernel can mount the file system type without loading a module.
+    pub fn supports(&self, fstype: &str) -> bool { self.get(fstype).is_some() }
+
+    /// Whether the file system type must be mounted from a block device, if it is supported.
+    pub fn requires_block_device(&self, fstype: &str) -> Option<bool> {
+        self.get(fstype).map(FilesystemInfo::requires_block_device)
+    }
+
+    /// Whether the file system type can be mounted, or which must first have its module loaded.
+    pub fn support(&self, fstype: &str) -> FilesystemSupport {
+        if self.supports(fstype) {
+            FilesystemSupport::Supported
+        } else if module_available(fstype) {
+            FilesystemSupport::Module
+        } else {
+            FilesystemSupport::Unsupported
+        }
+    }
+
+    /// Classify a file system type, using the `nodev` marks of this list for unknown types.
+    pub fn class(&self, fstype: &str) -> FilesystemClass {
+        FilesystemClass::new(fstype, self.requires_block_device(fstype) == Some(false))
+    }
+}
+
+/// Whether a file system type can be mounted by the running kernel.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub enum FilesystemSupport {
+    /// The type is registered with the kernel.
+    Supported,
+    /// The type is provided by a module of the running kernel, which is not loaded.
+    Module,
+    /// The type is neither registered, nor provided by a module.
+    Unsupported,
+}
+
+/// Iteratively parse the `/proc/filesystems` file.
+pub struct FilesystemIter<R> {
+    file:   R,
+    buffer: Vec<u8>,
+    line:   usize,
+    path:   Option<PathBuf>,
+}
+
+impl FilesystemIter<BufReader<File>> {
+    pub fn new() -> io::Result<Self> { Self::new_from_file("/proc/filesystems") }
+
+    pub fn new_from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let mut iter = Self::new_from_reader(BufReader::new(File::open(path)?));
+        iter.path = Some(path.to_path_buf());
+        Ok(iter)
+    }
+}
+
+impl<R: BufRead> FilesystemIter<R> {
+    pub fn new_from_reader(reader: R) -> Self {
+        Self { file: reader, buffer: Vec::with_capacity(32), line: 0, path: None }
+    }
+}
+
+impl<R: BufRead> Iterator for FilesystemIter<R> {
+    type Item = io::Result<FilesystemInfo>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.buffer.clear();
+            match self.file.read_until(b'\n', &mut self.buffer) {
+                Ok(0) => return None,
+                Ok(_) => {
+                    self.line += 1;
+                    if parse::fields(&self.buffer).next().is_some() {
+                        return Some(FilesystemInfo::from_bytes(&self.buffer).map_err(|why| {
+                            error::locate(why, Some(self.line), self.path.as_deref())
+                        }));
+                    }
+                }
+                Err(why) => return Some(Err(why)),
+            }
+        }
+    }
+}
+
+/// Returns true if a module of the running kernel provides the file system type.
+///
+/// The `fs-<type>` aliases of `/lib/modules/<release>/modules.alias` are checked first, followed
+/// by the names of the modules beneath `kernel/fs/` in `modules.dep`. Other modules, such as
+/// `loop` or `dm_mod`, are never taken to be file systems.
+pub fn module_available(fstype: &str) -> bool {
+    match fs::read_to_string("/proc/sys/kernel/osrelease") {
+        Ok(release) => module_in(&Path::new("/lib/modules").join(release.trim()), fstype),
+        Err(_) => false,
+    }
+}
+
+/// Returns true if a module of the given modules directory provides the file system type.
+fn module_in(modules: &Path, fstype: &str) -> bool {
+    let fstype = fstype.split('.').next().unwrap_or(fstype);
+    let alias = format!("fs-{}", fstype);
+    let has_alias = |aliases: String| {
+        aliases.lines().any(|line| {
+            let mut fields = line.split_whitespace();
+            fields.next() == Some("alias") && fields.next() == Some(alias.as_str())
+        })
+    };
+
+    let has_module = |dependencies: String| {
+        dependencies.lines().any(|line| {
+            let path = line.split(':').next().unwrap_or_default();
+            let module = path.rsplit('/').next().unwrap_or_default();
+            path.starts_with("kernel/fs/")
+                && module.split('.').next().unwrap_or_default().replace('-', "_") == fstype
+        })
+    };
+
+    matches!(fs::read_to_string(modules.join("modules.alias")).map(has_alias), Ok(true))
+        || matches!(fs::read_to_string(modules.join("modules.dep")).map(has_module), Ok(true))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classes() {
+        assert_eq!(FilesystemClass::new("proc", false), FilesystemClass::Virtual);
+        assert_eq!(FilesystemClass::new("nfs4", false), FilesystemClass::Network);
+        assert_eq!(FilesystemClass::new("fuse.sshfs", true), FilesystemClass::Network);
+        assert_eq!(FilesystemClass::new("fuse.mergerfs", true), FilesystemClass::Overlay);
+        assert_eq!(FilesystemClass::new("fuse.foo", true), FilesystemClass::Fuse);
+        assert_eq!(FilesystemClass::new("fuseblk", false), FilesystemClass::Block);
+        assert_eq!(FilesystemClass::new("ext4", false), FilesystemClass::Block);
+        assert_eq!(FilesystemClass::new("zonefs", true), FilesystemClass::Virtual);
+
+        // These are marked as nodev, but store data which is reported by `df`.
+        assert_eq!(FilesystemClass::new("virtiofs", true), FilesystemClass::Network);
+        assert_eq!(FilesystemClass::new("vboxsf", true), FilesystemClass::Network);
+        assert_eq!(FilesystemClass::new("zfs", true), FilesystemClass::Block);
+        assert_eq!(FilesystemClass::from_known("ext4"), None);
+
+        let mounts = MountList::parse_from(
+            "sysfs /sys sysfs rw 0 0
+             /dev/sda2 / ext4 rw 0 0
+             server:/export /mnt/nfs nfs4 rw 0 0
+             overlay /var/lib/docker/overlay2/merged overlay rw 0 0
+             mystery /mnt/mystery mysteryfs rw 0 0"
+                .lines(),
+        )
+        .unwrap();
+
+        let filesystems = FilesystemList::parse_from("nodev\tmysteryfs\n\text4".lines()).unwrap();
+        let classes =
+            mounts.0.iter().map(|mount| mount.class_with(Some(&filesystems))).collect::<Vec<_>>();
+        assert_eq!(
+            classes,
+            vec![
+                FilesystemClass::Virtual,
+                FilesystemClass::Block,
+                FilesystemClass::Network,
+                FilesystemClass::Overlay,
+                FilesystemClass::Virtual,
+            ]
+        );
+
+        assert_eq!(mounts.0[4].class_with(None), FilesystemClass::Virtual);
+        assert_eq!(mounts.0[1].class_with(None), FilesystemClass::Block);
+        assert_eq!(mounts.filter_class(FilesystemClass::Network).count(), 1);
+    }
+
+    #[test]
+    fn filesystems() {
+        let input = b"nodev\tsysfs\nnodev\tfuse\n\text4\n\tfuseblk\n\n";
+        let filesystems = FilesystemList::new_from_reader(&input[..]).unwrap();
+        assert_eq!(filesystems.0.len(), 4);
+        assert_eq!(filesystems.0[0], FilesystemInfo { name: "sysfs".into(), nodev: true });
+        assert_eq!(filesystems.0[2].to_string(), "\text4");
+        assert_eq!(
+            filesystems.0[0].to_string().parse::<FilesystemInfo>().unwrap(),
+            filesystems.0[0]
+        );
+
+        assert!(filesystems.supports("ext4"));
+        assert!(filesystems.supports("fuse.sshfs"));
+        assert!(!filesystems.supports("btrfs"));
+        assert_eq!(filesystems.requires_block_device("ext4"), Some(true));
+        assert_eq!(filesystems.requires_block_device("sysfs"), Some(false));
+        assert_eq!(filesystems.requires_block_device("btrfs"), None);
+        assert_eq!(filesystems.support("ext4"), FilesystemSupport::Supported);
+        assert_eq!(filesystems.support("not-a-filesystem"), FilesystemSupport::Unsupported);
+        assert_eq!(filesystems.class("sysfs"), FilesystemClass::Virtual);
+
+        assert!("nodev".parse::<FilesystemInfo>().is_err());
+        assert!("dev ext4".parse::<FilesystemInfo>().is_err());
+        assert!("nodev sysfs extra".parse::<FilesystemInfo>().is_err());
+    }
+
+    #[test]
+    fn modules() {
+        let modules =
+            std::env::temp_dir().join(format!("proc-mounts-modules-{}", std::process::id()));
+        fs::create_dir_all(&modules).unwrap();
+        fs::write(
+            modules.join("modules.alias"),
+            "alias fs-ntfs3 ntfs3\nalias devname:loop-control loop\nalias symbol:fs_kobj btrfs\n",
+        )
+        .unwrap();
+        fs::write(
+            modules.join("modules.dep"),
+            concat!(
+                "kernel/fs/btrfs/btrfs.ko.zst: kernel/lib/raid6/raid6_pq.ko.zst\n",
+                "kernel/fs/ntfs3/ntfs3.ko.zst:\n",
+                "kernel/drivers/block/loop.ko.zst:\n",
+                "kernel/drivers/md/dm-mod.ko.zst:\n",
+            ),
+        )
+        .unwrap();
+
+        let available = |fstype| module_in(&modules, fstype);
+        assert!(available("ntfs3"));
+        assert!(available("btrfs"));
+        assert!(!available("loop"));
+        assert!(!available("dm_mod"));
+        assert!(!available("devname:loop-control"));
+        assert!(!available("raid6_pq"));
+        assert!(!module_in(&modules.join("missing"), "ntfs3"));
+
+        fs::remove_dir_all(&modules).unwrap();
+    }
+}
@@ -0,0 +1,483 @@
+use crate::{
+    mount::{c_string, source_c_string},
+    MountError, MountInfo, MountOptions, MountSource,
+};
+use libc::{c_uint, c_ulong, c_void};
+use std::{
+    ffi::CString,
+    io,
+    ops::{BitOr, BitOrAssign},
+    os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd},
+    path::Path,
+    ptr,
+};
+
+const FSOPEN_CLOEXEC: c_uint = 0x1;
+const FSMOUNT_CLOEXEC: c_uint = 0x1;
+const FSCONFIG_SET_FLAG: c_uint = 0;
+const FSCONFIG_SET_STRING: c_uint = 1;
+const FSCONFIG_CMD_CREATE: c_uint = 6;
+const MOVE_MOUNT_F_EMPTY_PATH: c_uint = 0x4;
+const OPEN_TREE_CLONE: c_uint = 0x1;
+const OPEN_TREE_CLOEXEC: c_uint = libc::O_CLOEXEC as c_uint;
+
+/// The argument of `mount_setattr(2)`, as `struct mount_attr`.
+#[repr(C)]
+struct RawMountAttr {
+    attr_set:    u64,
+    attr_clr:    u64,
+    propagation: u64,
+    userns_fd:   u64,
+}
+
+/// The `MS_*` flags which are set on the super block through `fsconfig(2)`, by name.
+const SUPER_BLOCK_FLAGS: &[(c_ulong, &str)] = &[
+    (libc::MS_RDONLY, "ro"),
+    (libc::MS_SYNCHRONOUS, "sync"),
+    (libc::MS_DIRSYNC, "dirsync"),
+    (libc::MS_LAZYTIME, "lazytime"),
+    (libc::MS_MANDLOCK, "mand"),
+];
+
+/// The flags which cannot be expressed by a file system context.
+const UNSUPPORTED_FLAGS: c_ulong =
+    libc::MS_BIND | libc::MS_MOVE | libc::MS_REMOUNT | libc::MS_SILENT | libc::MS_I_VERSION;
+
+/// Attributes of a mount, as set by `fsmount(2)` and `mount_setattr(2)`.
+#[derive(Debug, Default, Copy, Clone, Hash, Eq, PartialEq)]
+pub struct MountAttr(u64);
+
+impl MountAttr {
+    /// The mask of the attributes which select how access times are updated, of which only one
+    /// may be set at a time.
+    pub const ATIME: MountAttr = MountAttr(0x70);
+    /// Access times are never updated.
+    pub const NOATIME: MountAttr = MountAttr(0x10);
+    /// Device files may not be accessed.
+    pub const NODEV: MountAttr = MountAttr(0x4);
+    /// Access times of directories are never updated.
+    pub const NODIRATIME: MountAttr = MountAttr(0x80);
+    /// Programs may not be executed.
+    pub const NOEXEC: MountAttr = MountAttr(0x8);
+    /// Set-user-ID and set-group-ID bits are ignored.
+    pub const NOSUID: MountAttr = MountAttr(0x2);
+    /// Symlinks are not followed when resolving paths.
+    pub const NOSYMFOLLOW: MountAttr = MountAttr(0x0020_0000);
+    /// The mount is read-only.
+    pub const RDONLY: MountAttr = MountAttr(0x1);
+    /// Access times are updated relative to the modify or change time, which is the default.
+    ///
+    /// As this is zero, it is selected by clearing the `ATIME` mask.
+    pub const RELATIME: MountAttr = MountAttr(0);
+    /// Access times are updated on every access.
+    pub const STRICTATIME: MountAttr = MountAttr(0x20);
+
+    /// No attributes, which gives a read-write mount with relative access times.
+    pub fn empty() -> Self { MountAttr(0) }
+
+    /// The attributes which correspond to the given `MS_*` flags.
+    pub fn from_flags(flags: c_ulong) -> Self {
+        [
+            (libc::MS_RDONLY, MountAttr::RDONLY),
+            (libc::MS_NOSUID, MountAttr::NOSUID),
+            (libc::MS_NODEV, MountAttr::NODEV),
+            (libc::MS_NOEXEC, MountAttr::NOEXEC),
+            (libc::MS_NOATIME, MountAttr::NOATIME),
+            (libc::MS_STRICTATIME, MountAttr::STRICTATIME),
+            (libc::MS_NODIRATIME, MountAttr::NODIRATIME),
+        ]
+        .iter()
+        .filter(|(flag, _)| flags & flag != 0)
+        .fold(MountAttr::empty(), |attr, &(_, other)| attr | other)
+    }
+
+    /// The raw `MOUNT_ATTR_*` value.
+    pub fn bits(self) -> u64 { self.0 }
+
+    /// Returns true if all of the `other` attributes are set.
+    pub fn contains(self, other: MountAttr) -> bool { self.0 & other.0 == other.0 }
+}
+
+impl BitOr for MountAttr {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self { MountAttr(self.0 | other.0) }
+}
+
+impl BitOrAssign for MountAttr {
+    fn bitor_assign(&mut self, other: Self) { self.0 |= other.0; }
+}
+
+/// The propagation type to give to a mount.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub enum PropagationType {
+    Private,
+    Shared,
+    Slave,
+    Unbindable,
+}
+
+impl PropagationType {
+    /// The propagation type selected by the given `MS_*` flags, if any.
+    pub fn from_flags(flags: c_ulong) -> Option<Self> {
+        if flags & libc::MS_UNBINDABLE != 0 {
+            Some(PropagationType::Unbindable)
+        } else if flags & libc::MS_PRIVATE != 0 {
+            Some(PropagationType::Private)
+        } else if flags & libc::MS_SLAVE != 0 {
+            Some(PropagationType::Slave)
+        } else if flags & libc::MS_SHARED != 0 {
+            Some(PropagationType::Shared)
+        } else {
+            None
+        }
+    }
+
+    /// The `MS_*` flag of the propagation type, as the `u64` of `struct mount_attr`.
+    fn flag(self) -> u64 {
+        match self {
+            PropagationType::Unbindable => 1 << 17,
+            PropagationType::Private => 1 << 18,
+            PropagationType::Slave => 1 << 19,
+            PropagationType::Shared => 1 << 20,
+        }
+    }
+}
+
+/// A file descriptor which is closed when it is dropped.
+#[derive(Debug)]
+struct Fd(RawFd);
+
+impl Drop for Fd {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.0);
+        }
+    }
+}
+
+fn check(result: libc::c_long) -> io::Result<RawFd> {
+    if result < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(result as RawFd)
+    }
+}
+
+/// Configures and creates a detached mount through a file system context, with `fsopen(2)`,
+/// `fsconfig(2)`, and `fsmount(2)`.
+///
+/// Nothing is visible in the file system until the mount is attached, so a mount which fails
+/// part way through its configuration leaves nothing behind.
+#[derive(Debug, Clone, Default)]
+pub struct MountBuilder {
+    fstype:      String,
+    source:      Option<MountSource>,
+    options:     MountOptions,
+    propagation: Option<PropagationType>,
+}
+
+impl MountBuilder {
+    /// Configure a new mount of the given file system type.
+    pub fn new<S: Into<String>>(fstype: S) -> Self {
+        MountBuilder { fstype: fstype.into(), ..Default::default() }
+    }
+
+    /// The source to mount, which is resolved to its device as with `MountInfo::mount`.
+    pub fn source<S: Into<MountSource>>(mut self, source: S) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+
+    /// The options of the mount.
+    ///
+    /// Flags such as `ro` and `nosuid` become attributes of the mount or its super block, and
+    /// every other kernel option is passed to the file system with `fsconfig(2)`. Userspace
+    /// options are ignored.
+    pub fn options<O: Into<MountOptions>>(mut self, options: O) -> Self {
+        self.options = options.into();
+        self
+    }
+
+    /// The propagation type of the mount, which overrides any propagation option.
+    pub fn propagation(mut self, propagation: PropagationType) -> Self {
+        self.propagation = Some(propagation);
+        self
+    }
+
+    /// Create the file system and a detached mount of it.
+    pub fn create(&self) -> Result<DetachedMount, MountError> {
+        let map_err = |why| MountError::from_os_error(why, &self.fstype);
+        let kernel = self.options.to_kernel();
+
+        if kernel.flags & UNSUPPORTED_FLAGS != 0 {
+            return Err(MountError::InvalidArgument);
+        }
+
+        let fstype = c_string(&self.fstype)?;
+        let context =
+            Fd(check(unsafe { libc::syscall(libc::SYS_fsopen, fstype.as_ptr(), FSOPEN_CLOEXEC) })
+                .map_err(map_err)?);
+
+        if let Some(ref source) = self.source {
+            fsconfig(
+                &context,
+                FSCONFIG_SET_STRING,
+                &c_string("source")?,
+                Some(&source_c_string(source)?),
+            )
+            .map_err(map_err)?;
+        }
+
+        for &(flag, name) in SUPER_BLOCK_FLAGS {
+            if kernel.flags & flag != 0 {
+                fsconfig(&context, FSCONFIG_SET_FLAG, &c_string(name)?, None).map_err(map_err)?;
+            }
+        }
+
+        for option in &MountOptions::from(kernel.data.as_str()) {
+            let key = c_string(&option.key)?;
+            match option.value {
+                Some(ref value) => {
+                    fsconfig(&context, FSCONFIG_SET_STRING, &key, Some(&c_string(value)?))
+                }
+                None => fsconfig(&context, FSCONFIG_SET_FLAG, &key, None),
+            }
+            .map_err(map_err)?;
+        }
+
+        check(unsafe {
+            libc::syscall(
+                libc::SYS_fsconfig,
+                context.0,
+                FSCONFIG_CMD_CREATE,
+                ptr::null::<c_void>(),
+                ptr::null::<c_void>(),
+                0,
+            )
+        })
+        .map_err(map_err)?;
+
+        let attr = MountAttr::from_flags(kernel.flags);
+        let mount = DetachedMount(Fd(check(unsafe {
+            libc::syscall(libc::SYS_fsmount, context.0, FSMOUNT_CLOEXEC, attr.bits() as c_uint)
+        })
+        .map_err(map_err)?));
+
+        if let Some(propagation) =
+            self.propagation.or_else(|| PropagationType::from_flags(kernel.flags))
+        {
+            mount.set_propagation(propagation, false)?;
+        }
+
+        Ok(mount)
+    }
+
+    /// Create the mount, and attach it at `target`.
+    pub fn mount<P: AsRef<Path>>(&self, target: P) -> Result<(), MountError> {
+        self.create()?.attach(target)
+    }
+}
+
+impl<'a> From<&'a MountInfo> for MountBuilder {
+    fn from(info: &'a MountInfo) -> Self {
+        MountBuilder::new(info.fstype.clone())
+            .source(info.source.clone())
+            .options(info.options.clone())
+    }
+}
+
+fn fsconfig(
+    context: &Fd,
+    command: c_uint,
+    key: &CString,
+    value: Option<&CString>,
+) -> io::Result<()> {
+    let value = value.map_or(ptr::null(), |value| value.as_ptr());
+    check(unsafe { libc::syscall(libc::SYS_fsconfig, context.0, command, key.as_ptr(), value, 0) })
+        .map(|_| ())
+}
+
+/// A mount which is not yet attached anywhere in the file system.
+///
+/// The mount, and every mount beneath it, is unmounted when this is dropped without being
+/// attached.
+#[derive(Debug)]
+pub struct DetachedMount(Fd);
+
+impl DetachedMount {
+    /// Create a detached bind mount of the mount at `path`, with `open_tree(2)`.
+    ///
+    /// If `recursive` is true, the mounts beneath `path` are included as well.
+    pub fn clone_tree<P: AsRef<Path>>(path: P, recursive: bool) -> Result<Self, MountError> {
+        let path = c_string(path.as_ref())?;
+        let mut flags = OPEN_TREE_CLONE | OPEN_TREE_CLOEXEC;
+        if recursive {
+            flags |= libc::AT_RECURSIVE as c_uint;
+        }
+
+        check(unsafe { libc::syscall(libc::SYS_open_tree, libc::AT_FDCWD, path.as_ptr(), flags) })
+            .map(|fd| DetachedMount(Fd(fd)))
+            .map_err(|why| MountError::from_os_error(why, ""))
+    }
+
+    /// Set and clear attributes of the mount with `mount_setattr(2)`, and of every mount
+    /// beneath it if `recursive` is true.
+    ///
+    /// The access time attributes replace one another, so the whole `ATIME` mask is cleared
+    /// whenever one of them is set or cleared. Clearing them restores `RELATIME`.
+    pub fn set_attr(
+        &self,
+        set: MountAttr,
+        mut clear: MountAttr,
+        recursive: bool,
+    ) -> Result<(), MountError> {
+        if (set.0 | clear.0) & MountAttr::ATIME.0 != 0 {
+            clear |= MountAttr::ATIME;
+        }
+
+        self.setattr(
+            RawMountAttr {
+                attr_set:    set.bits(),
+                attr_clr:    clear.bits(),
+                propagation: 0,
+                userns_fd:   0,
+            },
+            recursive,
+        )
+    }
+
+    /// Change the propagation type of the mount with `mount_setattr(2)`, and of every mount
+    /// beneath it if `recursive` is true.
+    pub fn set_propagation(
+        &self,
+        propagation: PropagationType,
+        recursive: bool,
+    ) -> Result<(), MountError> {
+        self.setattr(
+            RawMountAttr {
+                attr_set:    0,
+                attr_clr:    0,
+                propagation: propagation.flag(),
+                userns_fd:   0,
+            },
+            recursive,
+        )
+    }
+
+    fn setattr(&self, attr: RawMountAttr, recursive: bool) -> Result<(), MountError> {
+        let mut flags = libc::AT_EMPTY_PATH;
+        if recursive {
+            flags |= libc::AT_RECURSIVE;
+        }
+
+        let empty = CString::default();
+        check(unsafe {
+            libc::syscall(
+                libc::SYS_mount_setattr,
+                (self.0).0,
+                empty.as_ptr(),
+                flags as c_uint,
+                &attr as *const RawMountAttr,
+                std::mem::size_of::<RawMountAttr>(),
+            )
+        })
+        .map(|_| ())
+        .map_err(|why| MountError::from_os_error(why, ""))
+    }
+
+    /// Attach the mount at `target` with `move_mount(2)`.
+    pub fn attach<P: AsRef<Path>>(self, target: P) -> Result<(), MountError> {
+        let target = c_string(target.as_ref())?;
+        let empty = CString::default();
+        check(unsafe {
+            libc::syscall(
+                libc::SYS_move_mount,
+                (self.0).0,
+                empty.as_ptr(),
+                libc::AT_FDCWD,
+                target.as_ptr(),
+                MOVE_MOUNT_F_EMPTY_PATH,
+            )
+        })
+        .map(|_| ())
+        .map_err(|why| MountError::from_os_error(why, ""))
+    }
+}
+
+impl AsRawFd for DetachedMount {
+    fn as_raw_fd(&self) -> RawFd { (self.0).0 }
+}
+
+impl IntoRawFd for DetachedMount {
+    fn into_raw_fd(self) -> RawFd {
+        let fd = (self.0).0;
+        std::mem::forget(self);
+        fd
+    }
+}
+
+impl FromRawFd for DetachedMount {
+    /// Take ownership of a mount file descriptor, such as one returned by `fsmount(2)` or
+    /// `open_tree(2)`.
+    unsafe fn from_raw_fd(fd: RawFd) -> Self { DetachedMount(Fd(fd)) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attributes() {
+        let kernel = MountOptions::from("ro,nosuid,noatime,size=1m,shared").to_kernel();
+        let attr = MountAttr::from_flags(kernel.flags);
+        assert_eq!(attr, MountAttr::RDONLY | MountAttr::NOSUID | MountAttr::NOATIME);
+        assert!(attr.contains(MountAttr::RDONLY | MountAttr::NOSUID));
+        assert!(!attr.contains(MountAttr::NODEV));
+        assert_eq!(PropagationType::from_flags(kernel.flags), Some(PropagationType::Shared));
+        assert_eq!(std::mem::size_of::<RawMountAttr>(), 32);
+
+        for propagation in [
+            PropagationType::Private,
+            PropagationType::Shared,
+            PropagationType::Slave,
+            PropagationType::Unbindable,
+        ] {
+            assert_eq!(
+                PropagationType::from_flags(propagation.flag() as c_ulong),
+                Some(propagation)
+            );
+        }
+    }
+
+    #[test]
+    fn unsupported_options() {
+        for options in &["bind", "silent", "iversion"] {
+            let builder = MountBuilder::new("tmpfs").options(*options);
+            assert!(matches!(builder.create(), Err(MountError::InvalidArgument)));
+        }
+    }
+
+    #[test]
+    fn set_atime() {
+        let flags = |mount: &DetachedMount| {
+            let mut stat = unsafe { std::mem::zeroed::<libc::statvfs>() };
+            assert_eq!(unsafe { libc::fstatvfs(mount.as_raw_fd(), &mut stat) }, 0);
+            stat.f_flag
+        };
+
+        // Creating a mount requires privileges, and a kernel with the new mount API.
+        let mount = match MountBuilder::new("tmpfs").options("strictatime").create() {
+            Ok(mount) => mount,
+            Err(MountError::PermissionDenied(_)) | Err(MountError::Other(_)) => return,
+            Err(why) => panic!("failed to create a tmpfs: {}", why),
+        };
+
+        mount.set_attr(MountAttr::NOATIME, MountAttr::empty(), false).unwrap();
+        assert_ne!(flags(&mount) & libc::ST_NOATIME, 0);
+
+        mount.set_attr(MountAttr::RELATIME, MountAttr::NOATIME, false).unwrap();
+        assert_eq!(flags(&mount) & libc::ST_NOATIME, 0);
+        assert_ne!(flags(&mount) & libc::ST_RELATIME, 0);
+    }
+}
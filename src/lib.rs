@@ -1,4 +1,5 @@
-//! Provides easy access to data from the `/proc/swaps` and `/proc/mounts` files.
+//! Provides easy access to data from the `/proc/swaps`, `/proc/mounts`, and
+//! `/proc/self/mountinfo` files.
 //!
 //! ```rust,no_run
 //! extern crate proc_mounts;
@@ -21,7 +22,30 @@
 //! }
 //! ```
 
+#[cfg(feature = "tokio")]
+extern crate tokio_crate as tokio;
+
+#[cfg(feature = "tokio")]
+mod asynchronous;
+mod error;
+mod escape;
+mod filesystems;
+mod fsmount;
+mod mount;
+mod mountinfo;
 mod mounts;
+mod mountstats;
+mod namespace;
+mod parse;
+#[cfg(feature = "serde")]
+mod serialization;
 mod swaps;
+mod usage;
+mod watch;
 
-pub use self::{mounts::*, swaps::*};
\ No newline at end of file
+#[cfg(feature = "tokio")]
+pub use self::asynchronous::*;
+pub use self::{
+    error::ParseError, escape::Escaped, filesystems::*, fsmount::*, mount::*, mountinfo::*,
+    mounts::*, mountstats::*, namespace::*, swaps::*, usage::*, watch::*,
+};
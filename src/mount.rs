@@ -0,0 +1,303 @@
+use crate::{MountInfo, MountSource};
+use libc::{c_int, c_ulong};
+use std::{
+    error::Error,
+    ffi::{CString, OsStr},
+    fmt::{self, Display, Formatter},
+    io,
+    ops::{BitOr, BitOrAssign},
+    os::unix::ffi::OsStrExt,
+    path::Path,
+    ptr,
+};
+
+/// The flags which change the propagation type of an existing mount, rather than mounting.
+const PROPAGATION: c_ulong =
+    libc::MS_SHARED | libc::MS_PRIVATE | libc::MS_SLAVE | libc::MS_UNBINDABLE;
+
+/// The flags which are ignored when creating a bind mount, and must be applied by remounting it.
+const BIND_REMOUNT: c_ulong = libc::MS_RDONLY
+    | libc::MS_NOSUID
+    | libc::MS_NODEV
+    | libc::MS_NOEXEC
+    | libc::MS_NOATIME
+    | libc::MS_NODIRATIME
+    | libc::MS_RELATIME
+    | libc::MS_STRICTATIME;
+
+/// An error which occurred while mounting or unmounting a file system.
+#[derive(Debug)]
+pub enum MountError {
+    /// The caller lacks the privileges to mount or unmount, or may not access a path. Holds the
+    /// `errno` value that was returned, which is `EPERM` or `EACCES`.
+    PermissionDenied(i32),
+    /// The target is busy, or the source is already mounted (`EBUSY`).
+    Busy,
+    /// The source or target does not exist (`ENOENT`).
+    NotFound,
+    /// The file system type is not supported by the kernel (`ENODEV`).
+    UnsupportedFileSystem(String),
+    /// The source is not a block device (`ENOTBLK`).
+    NotBlockDevice,
+    /// The target is not a mount point, or the flags or options are invalid (`EINVAL`).
+    InvalidArgument,
+    /// The source is read-only, but a read-write mount was requested (`EROFS`).
+    ReadOnly,
+    /// A path, file system type, or option contains a NUL byte.
+    NulByte,
+    /// The source could not be resolved to a device.
+    Source(io::Error),
+    /// Any other error returned by the system call.
+    Other(io::Error),
+}
+
+impl MountError {
+    /// Map the error of a failed `mount(2)` or `umount2(2)` call.
+    pub(crate) fn from_os_error(error: io::Error, fstype: &str) -> Self {
+        match error.raw_os_error() {
+            Some(errno @ libc::EPERM) | Some(errno @ libc::EACCES) => {
+                MountError::PermissionDenied(errno)
+            }
+            Some(libc::EBUSY) => MountError::Busy,
+            Some(libc::ENOENT) => MountError::NotFound,
+            Some(libc::ENODEV) => MountError::UnsupportedFileSystem(fstype.into()),
+            Some(libc::ENOTBLK) => MountError::NotBlockDevice,
+            Some(libc::EINVAL) => MountError::InvalidArgument,
+            Some(libc::EROFS) => MountError::ReadOnly,
+            _ => MountError::Other(error),
+        }
+    }
+
+    /// The `errno` value which corresponds to this error, if any.
+    pub fn raw_os_error(&self) -> Option<i32> {
+        match self {
+            MountError::PermissionDenied(errno) => Some(*errno),
+            MountError::Busy => Some(libc::EBUSY),
+            MountError::NotFound => Some(libc::ENOENT),
+            MountError::UnsupportedFileSystem(_) => Some(libc::ENODEV),
+            MountError::NotBlockDevice => Some(libc::ENOTBLK),
+            MountError::InvalidArgument => Some(libc::EINVAL),
+            MountError::ReadOnly => Some(libc::EROFS),
+            MountError::NulByte => None,
+            MountError::Source(ref why) | MountError::Other(ref why) => why.raw_os_error(),
+        }
+    }
+}
+
+impl Display for MountError {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        match self {
+            MountError::PermissionDenied(_) => fmt.write_str("permission denied"),
+            MountError::Busy => fmt.write_str("target is busy"),
+            MountError::NotFound => fmt.write_str("source or target does not exist"),
+            MountError::UnsupportedFileSystem(ref fstype) => {
+                write!(fmt, "file system type {} is not supported", fstype)
+            }
+            MountError::NotBlockDevice => fmt.write_str("source is not a block device"),
+            MountError::InvalidArgument => fmt.write_str("invalid mount point, flags, or options"),
+            MountError::ReadOnly => fmt.write_str("source is read-only"),
+            MountError::NulByte => fmt.write_str("argument contains a NUL byte"),
+            MountError::Source(ref why) => write!(fmt, "failed to resolve source: {}", why),
+            MountError::Other(ref why) => write!(fmt, "{}", why),
+        }
+    }
+}
+
+impl Error for MountError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MountError::Source(ref why) | MountError::Other(ref why) => Some(why),
+            _ => None,
+        }
+    }
+}
+
+impl From<MountError> for io::Error {
+    fn from(error: MountError) -> Self {
+        match error {
+            MountError::Source(why) | MountError::Other(why) => why,
+            MountError::NulByte => io::Error::new(io::ErrorKind::InvalidInput, error),
+            // Every other variant corresponds to an `errno` value.
+            error => io::Error::from_raw_os_error(error.raw_os_error().unwrap_or(libc::EIO)),
+        }
+    }
+}
+
+/// Flags which change how a file system is unmounted by `umount2(2)`.
+#[derive(Debug, Default, Copy, Clone, Hash, Eq, PartialEq)]
+pub struct UnmountFlags(c_int);
+
+impl UnmountFlags {
+    /// Detach the mount immediately, and clean it up once it is no longer busy.
+    pub const DETACH: UnmountFlags = UnmountFlags(libc::MNT_DETACH);
+    /// Mark the mount as expired, so that a second unmount with this flag unmounts it if it has
+    /// not been accessed in the meantime.
+    pub const EXPIRE: UnmountFlags = UnmountFlags(libc::MNT_EXPIRE);
+    /// Force the unmount, even if the file system is busy.
+    pub const FORCE: UnmountFlags = UnmountFlags(libc::MNT_FORCE);
+    /// Do not follow the target if it is a symlink.
+    pub const NOFOLLOW: UnmountFlags = UnmountFlags(libc::UMOUNT_NOFOLLOW);
+
+    /// No flags, which unmounts the file system only if it is not busy.
+    pub fn empty() -> Self { UnmountFlags(0) }
+
+    /// The raw value which is passed to `umount2(2)`.
+    pub fn bits(self) -> c_int { self.0 }
+
+    /// Returns true if all of the `other` flags are set.
+    pub fn contains(self, other: UnmountFlags) -> bool { self.0 & other.0 == other.0 }
+}
+
+impl BitOr for UnmountFlags {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self { UnmountFlags(self.0 | other.0) }
+}
+
+impl BitOrAssign for UnmountFlags {
+    fn bitor_assign(&mut self, other: Self) { self.0 |= other.0; }
+}
+
+pub(crate) fn c_string<S: AsRef<OsStr>>(value: S) -> Result<CString, MountError> {
+    CString::new(value.as_ref().as_bytes()).map_err(|_| MountError::NulByte)
+}
+
+/// The source as it is passed to the kernel, with device sources resolved to their path.
+pub(crate) fn source_c_string(source: &MountSource) -> Result<CString, MountError> {
+    match source {
+        MountSource::Network(ref spec) | MountSource::Pseudo(ref spec) => c_string(spec),
+        source => c_string(source.resolve().map_err(MountError::Source)?),
+    }
+}
+
+fn mount_raw(
+    source: Option<&CString>,
+    target: &CString,
+    fstype: Option<&CString>,
+    flags: c_ulong,
+    data: Option<&CString>,
+) -> io::Result<()> {
+    let as_ptr = |value: Option<&CString>| value.map_or(ptr::null(), |value| value.as_ptr());
+
+    let result = unsafe {
+        libc::mount(
+            as_ptr(source),
+            target.as_ptr(),
+            as_ptr(fstype),
+            flags,
+            as_ptr(data) as *const libc::c_void,
+        )
+    };
+
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+impl MountInfo {
+    /// Mount the source at the destination, with the file system type and options of this entry.
+    ///
+    /// `UUID=`, `LABEL=`, and similar sources are resolved to their device first. Userspace
+    /// options such as `noauto` are ignored, propagation options such as `shared` are applied
+    /// after mounting, and the flags of a bind mount are applied by remounting it, as `mount(8)`
+    /// does.
+    pub fn mount(&self) -> Result<(), MountError> {
+        let kernel = self.options.to_kernel();
+
+        let source = source_c_string(&self.source)?;
+
+        let target = c_string(&self.dest)?;
+        let fstype = c_string(&self.fstype)?;
+        let data = if kernel.data.is_empty() { None } else { Some(c_string(&kernel.data)?) };
+
+        let map_err = |why| MountError::from_os_error(why, &self.fstype);
+        let propagation = kernel.flags & PROPAGATION;
+        let flags = kernel.flags & !(PROPAGATION | libc::MS_REC);
+        let recursive = kernel.flags & libc::MS_REC;
+
+        // As with `mount --make-private`, only the propagation type is changed when no source,
+        // file system type, or other options are given.
+        let is_unset = |value: &OsStr| value.is_empty() || value == "none";
+        let propagation_only = propagation != 0
+            && flags == 0
+            && data.is_none()
+            && is_unset(OsStr::from_bytes(source.as_bytes()))
+            && is_unset(self.fstype.as_ref());
+
+        if propagation_only {
+            // Nothing is mounted.
+        } else if flags & (libc::MS_BIND | libc::MS_REMOUNT) == libc::MS_BIND {
+            mount_raw(Some(&source), &target, None, libc::MS_BIND | recursive, None)
+                .map_err(map_err)?;
+
+            if flags & BIND_REMOUNT != 0 {
+                let flags = libc::MS_REMOUNT | libc::MS_BIND | (flags & BIND_REMOUNT);
+                mount_raw(None, &target, None, flags, None).map_err(map_err)?;
+            }
+        } else {
+            mount_raw(Some(&source), &target, Some(&fstype), flags, data.as_ref())
+                .map_err(map_err)?;
+        }
+
+        if propagation != 0 {
+            mount_raw(None, &target, None, propagation | recursive, None).map_err(map_err)?;
+        }
+
+        Ok(())
+    }
+
+    /// Unmount the file system which is mounted at the destination of this entry.
+    pub fn unmount(&self, flags: UnmountFlags) -> Result<(), MountError> {
+        unmount(&self.dest, flags)
+    }
+}
+
+/// Unmount the file system which is mounted at `target`, with `umount2(2)`.
+pub fn unmount<P: AsRef<Path>>(target: P, flags: UnmountFlags) -> Result<(), MountError> {
+    let target = c_string(target.as_ref())?;
+    if unsafe { libc::umount2(target.as_ptr(), flags.bits()) } == 0 {
+        Ok(())
+    } else {
+        Err(MountError::from_os_error(io::Error::last_os_error(), ""))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unmount_flags() {
+        let flags = UnmountFlags::DETACH | UnmountFlags::NOFOLLOW;
+        assert_eq!(flags.bits(), libc::MNT_DETACH | libc::UMOUNT_NOFOLLOW);
+        assert!(flags.contains(UnmountFlags::DETACH));
+        assert!(!flags.contains(UnmountFlags::FORCE));
+        assert_eq!(UnmountFlags::default(), UnmountFlags::empty());
+    }
+
+    #[test]
+    fn errors() {
+        let error = MountError::from_os_error(io::Error::from_raw_os_error(libc::ENODEV), "zfs");
+        assert_eq!(error.to_string(), "file system type zfs is not supported");
+        assert_eq!(io::Error::from(error).raw_os_error(), Some(libc::ENODEV));
+
+        let error = MountError::from_os_error(io::Error::from_raw_os_error(libc::EBUSY), "");
+        assert!(matches!(error, MountError::Busy));
+        assert_eq!(io::Error::from(error).raw_os_error(), Some(libc::EBUSY));
+
+        for &errno in &[libc::EPERM, libc::EACCES] {
+            let error = MountError::from_os_error(io::Error::from_raw_os_error(errno), "");
+            assert!(matches!(error, MountError::PermissionDenied(_)));
+            assert_eq!(io::Error::from(error).raw_os_error(), Some(errno));
+        }
+
+        let error = io::Error::from(MountError::NulByte);
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+
+        let mount =
+            MountInfo { dest: "/mnt/a\0b".into(), fstype: "tmpfs".into(), ..Default::default() };
+        assert!(matches!(mount.unmount(UnmountFlags::empty()), Err(MountError::NulByte)));
+    }
+}
@@ -0,0 +1,196 @@
+use super::{OptionalField, Propagation};
+use crate::{
+    escape::{self, Escaped},
+    parse, MountFlag, MountInfo, MountOptions, MountSource,
+};
+use std::{
+    fmt::{self, Display, Formatter},
+    io,
+    path::PathBuf,
+    str::FromStr,
+};
+
+/// A mount entry from `/proc/self/mountinfo`, which describes a mount in greater detail than
+/// the `/proc/mounts` format.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Default, Clone, Hash, Eq, PartialEq)]
+pub struct MountInfoEntry {
+    /// A unique ID for the mount, which may be reused after the mount is unmounted.
+    pub mount_id:        u32,
+    /// The ID of the parent mount, or of itself for the root of the mount namespace.
+    pub parent_id:       u32,
+    /// The major ID of the device backing the mounted file system.
+    pub major:           u32,
+    /// The minor ID of the device backing the mounted file system.
+    pub minor:           u32,
+    /// The directory within the file system which forms the root of this mount.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::os_string"))]
+    pub root:            PathBuf,
+    /// Where the source is mounted, relative to the root of the process.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::os_string"))]
+    pub dest:            PathBuf,
+    /// Options which apply to this mount point.
+    pub mount_options:   MountOptions,
+    /// Optional fields in the form of `tag[:value]`, which describe mount propagation.
+    pub optional_fields: Vec<OptionalField>,
+    /// The type of the mounted file system.
+    pub fstype:          String,
+    /// The source which is mounted.
+    pub source:          MountSource,
+    /// Options which apply to the super block of the file system.
+    pub super_options:   MountOptions,
+}
+
+impl Display for MountInfoEntry {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        write!(
+            fmt,
+            "{} {} {}:{} {} {} {}",
+            self.mount_id,
+            self.parent_id,
+            self.major,
+            self.minor,
+            Escaped(self.root.as_os_str()),
+            Escaped(self.dest.as_os_str()),
+            self.mount_options
+        )?;
+
+        for field in &self.optional_fields {
+            write!(fmt, " {}", field)?;
+        }
+
+        write!(fmt, " - {} {} {}", self.fstype, self.source, self.super_options)
+    }
+}
+
+impl FromStr for MountInfoEntry {
+    type Err = io::Error;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> { Self::from_bytes(line.as_bytes()) }
+}
+
+impl MountInfoEntry {
+    /// Parse a `/proc/self/mountinfo`-like line.
+    ///
+    /// The root, dest, and source fields may contain any bytes, including those which are not
+    /// valid UTF-8.
+    pub fn from_bytes(line: &[u8]) -> io::Result<Self> {
+        let line = parse::Line(line);
+        let mut parts = line.fields();
+
+        let mount_id = line.next(&mut parts, "mount ID")?;
+        let parent_id = line.next(&mut parts, "parent ID")?;
+        let device = line.next(&mut parts, "major:minor")?;
+        let root = line.next(&mut parts, "root")?;
+        let dest = line.next(&mut parts, "dest")?;
+        let mount_options = line.next(&mut parts, "mount options")?;
+
+        let mut optional_fields = Vec::new();
+        loop {
+            match parts.next() {
+                Some((_, b"-")) => break,
+                Some(field) => {
+                    let name = "optional field";
+                    let value = line.utf8(field, name, "optional field is not UTF-8")?;
+                    optional_fields.push(line.within(
+                        field.0,
+                        name,
+                        value.parse::<OptionalField>(),
+                    )?);
+                }
+                None => return Err(line.missing("optional fields separator")),
+            }
+        }
+
+        let fstype = line.next(&mut parts, "type")?;
+        let source = line.next(&mut parts, "source")?;
+        let super_options = line.next(&mut parts, "super options")?;
+
+        let (offset, device) = device;
+        let separator = device.iter().position(|&byte| byte == b':');
+        let separator = separator
+            .ok_or_else(|| line.field_error(offset, "major:minor", "invalid major:minor"))?;
+        let major = (offset, &device[..separator]);
+        let minor = (offset + separator + 1, &device[separator + 1..]);
+
+        Ok(MountInfoEntry {
+            mount_id: line.number(mount_id, "mount ID", "mount ID is not a number")?,
+            parent_id: line.number(parent_id, "parent ID", "parent ID is not a number")?,
+            major: line.number(major, "major ID", "major ID is not a number")?,
+            minor: line.number(minor, "minor ID", "minor ID is not a number")?,
+            root: PathBuf::from(line.decode(root, "root")?),
+            dest: PathBuf::from(line.decode(dest, "dest")?),
+            mount_options: line
+                .utf8(mount_options, "mount options", "mount options are not UTF-8")?
+                .into(),
+            optional_fields,
+            fstype: line.utf8(fstype, "type", "type is not UTF-8")?.to_owned(),
+            source: MountSource::from(line.decode(source, "source")?),
+            super_options: line
+                .utf8(super_options, "super options", "super options are not UTF-8")?
+                .into(),
+        })
+    }
+
+    /// Write the entry as a `/proc/self/mountinfo`-like line, without a trailing newline.
+    ///
+    /// Unlike `Display`, bytes which are not valid UTF-8 are written as they are.
+    pub fn write_bytes<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut line =
+            format!("{} {} {}:{} ", self.mount_id, self.parent_id, self.major, self.minor)
+                .into_bytes();
+
+        escape::encode(self.root.as_os_str(), &mut line);
+        line.push(b' ');
+        escape::encode(self.dest.as_os_str(), &mut line);
+        line.push(b' ');
+        line.extend_from_slice(self.mount_options.to_string().as_bytes());
+
+        for field in &self.optional_fields {
+            line.extend_from_slice(format!(" {}", field).as_bytes());
+        }
+
+        line.extend_from_slice(format!(" - {} ", self.fstype).as_bytes());
+        self.source.encode(&mut line);
+        line.push(b' ');
+        line.extend_from_slice(self.super_options.to_string().as_bytes());
+
+        writer.write_all(&line)
+    }
+
+    /// The propagation type of this mount.
+    pub fn propagation(&self) -> Propagation { Propagation::from_fields(&self.optional_fields) }
+
+    /// The dominant peer group that this slave mount receives propagation from, if the master
+    /// peer group is not visible from the root of this process.
+    pub fn propagate_from(&self) -> Option<u32> {
+        self.optional_fields.iter().find_map(|field| match *field {
+            OptionalField::PropagateFrom(id) => Some(id),
+            _ => None,
+        })
+    }
+}
+
+impl From<MountInfoEntry> for MountInfo {
+    /// Converts the entry into the `/proc/mounts` representation of the same mount.
+    fn from(entry: MountInfoEntry) -> Self {
+        // The read-only state of the mount point takes precedence over that of the super block.
+        let mut options = entry.mount_options;
+        for option in entry.super_options {
+            let is_access_mode =
+                matches!(option.flag(), Some(MountFlag::ReadOnly | MountFlag::ReadWrite));
+            if !is_access_mode && !options.iter().any(|other| *other == option) {
+                options.push(option);
+            }
+        }
+
+        MountInfo {
+            source: entry.source,
+            dest: entry.dest,
+            fstype: entry.fstype,
+            options,
+            dump: 0,
+            pass: 0,
+        }
+    }
+}
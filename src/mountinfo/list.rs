@@ -0,0 +1,203 @@
+use super::{MountInfoEntry, MountInfoIter};
+use crate::{
+    mounts::{take_changes, unmatched, MountDiff},
+    parse,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    io::{self, BufRead},
+    os::unix::{fs::MetadataExt, io::AsRawFd},
+    path::Path,
+};
+
+/// A list of parsed mount entries from `/proc/self/mountinfo`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Default, Clone, Hash, Eq, PartialEq)]
+pub struct MountInfoList(pub Vec<MountInfoEntry>);
+
+impl MountInfoList {
+    /// Parse mounts given from an iterator of mountinfo entry lines.
+    pub fn parse_from<'a, I: Iterator<Item = &'a str>>(lines: I) -> io::Result<MountInfoList> {
+        parse::lines(lines, MountInfoEntry::from_bytes).map(MountInfoList)
+    }
+
+    /// Read a new list of mounts into memory from `/proc/self/mountinfo`.
+    pub fn new() -> io::Result<MountInfoList> {
+        Ok(MountInfoList(MountInfoIter::new()?.collect::<io::Result<Vec<MountInfoEntry>>>()?))
+    }
+
+    /// Read a new list of mounts into memory from any mountinfo-like file.
+    pub fn new_from_file<P: AsRef<Path>>(path: P) -> io::Result<MountInfoList> {
+        Ok(MountInfoList(
+            MountInfoIter::new_from_file(path)?.collect::<io::Result<Vec<MountInfoEntry>>>()?,
+        ))
+    }
+
+    /// Read a new list of mounts into memory from `/proc/<pid>/mountinfo`.
+    pub fn new_from_pid(pid: u32) -> io::Result<MountInfoList> {
+        Ok(MountInfoList(
+            MountInfoIter::new_from_pid(pid)?.collect::<io::Result<Vec<MountInfoEntry>>>()?,
+        ))
+    }
+
+    /// Read a new list of mounts into memory from the process referred to by a pidfd.
+    pub fn new_from_pidfd<F: AsRawFd>(pidfd: &F) -> io::Result<MountInfoList> {
+        Ok(MountInfoList(
+            MountInfoIter::new_from_pidfd(pidfd)?.collect::<io::Result<Vec<MountInfoEntry>>>()?,
+        ))
+    }
+
+    /// Read a new list of mounts into memory from the mount namespace referred to by a
+    /// namespace file descriptor.
+    pub fn new_from_namespace<F: AsRawFd>(namespace: &F) -> io::Result<MountInfoList> {
+        Ok(MountInfoList(
+            MountInfoIter::new_from_namespace(namespace)?
+                .collect::<io::Result<Vec<MountInfoEntry>>>()?,
+        ))
+    }
+
+    /// Read a new list of mounts into memory from any mountinfo-like reader.
+    pub fn new_from_reader<R: BufRead>(reader: R) -> io::Result<MountInfoList> {
+        Ok(MountInfoList(
+            MountInfoIter::new_from_reader(reader).collect::<io::Result<Vec<MountInfoEntry>>>()?,
+        ))
+    }
+
+    /// Find the mount with the given mount ID.
+    pub fn get_mount_by_id(&self, id: u32) -> Option<&MountInfoEntry> {
+        self.0.iter().find(|mount| mount.mount_id == id)
+    }
+
+    /// Find the last mount which has the `path` destination.
+    ///
+    /// Later entries are mounted over earlier entries, so the last match is the visible mount.
+    pub fn get_mount_by_dest<P: AsRef<Path>>(&self, path: P) -> Option<&MountInfoEntry> {
+        self.0.iter().rev().find(|mount| mount.dest == path.as_ref())
+    }
+
+    /// Find the mount which contains the given `path`.
+    ///
+    /// The mount with the longest destination that is a parent of `path` is chosen, comparing
+    /// whole path components. If several mounts share that destination, the last one is
+    /// chosen, as it is mounted over the others. The `path` is not resolved, so it should be
+    /// absolute and free of symlinks.
+    pub fn get_mount_containing<P: AsRef<Path>>(&self, path: P) -> Option<&MountInfoEntry> {
+        let path = path.as_ref();
+        self.0
+            .iter()
+            .filter(|mount| path.starts_with(&mount.dest))
+            .max_by_key(|mount| mount.dest.components().count())
+    }
+
+    /// Find the mount which contains the file at the given `path`.
+    ///
+    /// Symlinks in the path are resolved, and the device number of the file is compared with
+    /// that of each candidate mount, so that the answer is correct even when the list is out
+    /// of date with the visible mount table. If no candidate has a matching device, such as
+    /// for files within a btrfs subvolume, the lexical match is returned.
+    pub fn get_mount_for_file<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> io::Result<Option<&MountInfoEntry>> {
+        let path = fs::canonicalize(path)?;
+        let (major, minor) = device_numbers(fs::metadata(&path)?.dev());
+
+        let by_device = self
+            .0
+            .iter()
+            .filter(|mount| mount.major == major && mount.minor == minor)
+            .filter(|mount| path.starts_with(&mount.dest))
+            .max_by_key(|mount| mount.dest.components().count());
+
+        Ok(by_device.or_else(|| self.get_mount_containing(&path)))
+    }
+
+    /// Find the first mount which has the source `path`.
+    pub fn get_mount_by_source<P: AsRef<Path>>(&self, path: P) -> Option<&MountInfoEntry> {
+        self.0.iter().find(|mount| mount.source == path.as_ref())
+    }
+
+    /// Iterate through each mount which is backed by the given `major:minor` device.
+    pub fn get_mounts_by_device(
+        &self,
+        major: u32,
+        minor: u32,
+    ) -> impl Iterator<Item = &MountInfoEntry> {
+        self.0.iter().filter(move |mount| mount.major == major && mount.minor == minor)
+    }
+
+    /// Group each shared mount by the peer group that it belongs to.
+    pub fn peer_groups(&self) -> BTreeMap<u32, Vec<&MountInfoEntry>> {
+        let mut groups = BTreeMap::<u32, Vec<&MountInfoEntry>>::new();
+        for mount in &self.0 {
+            if let Some(group) = mount.propagation().peer_group() {
+                groups.entry(group).or_default().push(mount);
+            }
+        }
+
+        groups
+    }
+
+    /// Iterate through each mount which is a member of the given peer group.
+    pub fn peers(&self, peer_group: u32) -> impl Iterator<Item = &MountInfoEntry> {
+        self.0.iter().filter(move |mount| mount.propagation().peer_group() == Some(peer_group))
+    }
+
+    /// Iterate through each mount which receives propagation from the given peer group.
+    pub fn slaves(&self, peer_group: u32) -> impl Iterator<Item = &MountInfoEntry> {
+        self.0.iter().filter(move |mount| mount.propagation().master() == Some(peer_group))
+    }
+
+    /// Map each master peer group to the peer groups which are slaves of it.
+    ///
+    /// Slave mounts which are not shared themselves do not form a peer group, and are
+    /// therefore only reachable through `slaves`.
+    pub fn slave_peer_groups(&self) -> BTreeMap<u32, BTreeSet<u32>> {
+        let mut relationships = BTreeMap::<u32, BTreeSet<u32>>::new();
+        for mount in &self.0 {
+            let propagation = mount.propagation();
+            if let (Some(group), Some(master)) = (propagation.peer_group(), propagation.master()) {
+                relationships.entry(master).or_default().insert(group);
+            }
+        }
+
+        relationships
+    }
+
+    /// Compare this list with a newer list of mounts.
+    ///
+    /// Mounts are keyed by their mount ID, so a mount is moved if its ID is found at another
+    /// destination. An ID which was reused by a different file system is reported as a removed
+    /// and an added mount.
+    pub fn diff(&self, new: &MountInfoList) -> MountDiff<MountInfoEntry> {
+        let (mut old, mut new) = unmatched(&self.0, &new.0);
+
+        let same_mount = |old: &MountInfoEntry, new: &MountInfoEntry| {
+            old.mount_id == new.mount_id
+                && (old.major, old.minor) == (new.major, new.minor)
+                && old.root == new.root
+                && old.source == new.source
+                && old.fstype == new.fstype
+        };
+
+        let moved = take_changes(&mut old, &mut new, |old, new| {
+            same_mount(old, new) && old.dest != new.dest
+        });
+        let remounted = take_changes(&mut old, &mut new, same_mount);
+
+        MountDiff {
+            added: new.into_iter().cloned().collect(),
+            removed: old.into_iter().cloned().collect(),
+            remounted,
+            moved,
+        }
+    }
+}
+
+/// Split a `dev_t` into its major and minor numbers, as the glibc `major` and `minor` do.
+fn device_numbers(dev: u64) -> (u32, u32) {
+    let major = ((dev >> 8) & 0xfff) | ((dev >> 32) & !0xfff);
+    let minor = (dev & 0xff) | ((dev >> 12) & !0xff);
+    (major as u32, minor as u32)
+}
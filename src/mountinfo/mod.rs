@@ -0,0 +1,170 @@
+mod entry;
+mod iter;
+mod list;
+mod propagation;
+mod statmount;
+mod tree;
+
+pub use self::{entry::*, iter::*, list::*, propagation::*, statmount::*, tree::*};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MountInfo, MountSource};
+    use std::path::PathBuf;
+
+    const SAMPLE: &str = r#"22 28 0:21 / /sys rw,nosuid,nodev,noexec,relatime shared:7 - sysfs sysfs rw
+23 28 0:22 / /proc rw,nosuid,nodev,noexec,relatime shared:14 - proc proc rw
+28 1 8:2 / / rw,noatime shared:1 - ext4 /dev/sda2 rw,errors=remount-ro
+31 28 8:1 / /boot/efi rw,relatime shared:15 - vfat /dev/sda1 rw,fmask=0077,dmask=0077
+40 28 8:6 /home /mnt/my\040data rw,noatime master:1 - ext4 /dev/sda6 rw
+41 28 0:45 / /run/user/1000 rw,nosuid,nodev - tmpfs tmpfs rw,size=3291048k,mode=700
+42 40 8:6 /home/shared /mnt/shared rw,noatime shared:20 master:1 - ext4 /dev/sda6 rw
+43 28 8:6 /home/shared /srv/shared rw,noatime shared:20 master:1 propagate_from:1 - ext4 /dev/sda6 rw
+44 28 0:46 / /srv/sealed rw unbindable - tmpfs tmpfs rw"#;
+
+    #[test]
+    fn mountinfo() {
+        let mounts = MountInfoList::parse_from(SAMPLE.lines()).unwrap();
+
+        assert_eq!(
+            mounts.get_mount_by_id(40).unwrap(),
+            &MountInfoEntry {
+                mount_id:        40,
+                parent_id:       28,
+                major:           8,
+                minor:           6,
+                root:            PathBuf::from("/home"),
+                dest:            PathBuf::from("/mnt/my data"),
+                mount_options:   "rw,noatime".into(),
+                optional_fields: vec![OptionalField::Master(1)],
+                fstype:          "ext4".into(),
+                source:          MountSource::Path(PathBuf::from("/dev/sda6")),
+                super_options:   "rw".into(),
+            }
+        );
+
+        assert!(mounts.get_mount_by_id(41).unwrap().optional_fields.is_empty());
+        assert_eq!(mounts.get_mount_by_dest("/").unwrap().mount_id, 28);
+        assert_eq!(
+            mounts.get_mount_by_source("/dev/sda1").unwrap().dest,
+            PathBuf::from("/boot/efi")
+        );
+        assert_eq!(mounts.get_mounts_by_device(8, 2).count(), 1);
+        assert_eq!(mounts.get_mount_containing("/mnt/my data/user").unwrap().mount_id, 40);
+        assert_eq!(mounts.get_mount_containing("/mnt/my").unwrap().mount_id, 28);
+
+        assert_eq!(
+            mounts.get_mount_by_id(40).unwrap().to_string(),
+            "40 28 8:6 /home /mnt/my\\040data rw,noatime master:1 - ext4 /dev/sda6 rw"
+        );
+        assert_eq!(
+            mounts.get_mount_by_id(31).unwrap().to_string(),
+            "31 28 8:1 / /boot/efi rw,relatime shared:15 - vfat /dev/sda1 rw,fmask=0077,dmask=0077"
+        );
+
+        let mount = MountInfo::from(mounts.get_mount_by_id(31).unwrap().clone());
+        assert_eq!(mount.options, "rw,relatime,fmask=0077,dmask=0077".into());
+    }
+
+    #[test]
+    fn mountinfo_invalid() {
+        assert!("22 28 0:21 / /sys rw shared:7 sysfs sysfs rw".parse::<MountInfoEntry>().is_err());
+        assert!("22 28 021 / /sys rw - sysfs sysfs rw".parse::<MountInfoEntry>().is_err());
+    }
+
+    #[test]
+    fn propagation() {
+        let mounts = MountInfoList::parse_from(SAMPLE.lines()).unwrap();
+        let propagation = |id| mounts.get_mount_by_id(id).unwrap().propagation();
+
+        assert_eq!(propagation(28), Propagation::Shared { peer_group: 1 });
+        assert_eq!(propagation(40), Propagation::Slave { master: 1 });
+        assert_eq!(propagation(41), Propagation::Private);
+        assert_eq!(propagation(42), Propagation::SharedAndSlave { peer_group: 20, master: 1 });
+        assert_eq!(propagation(44), Propagation::Unbindable);
+        assert!(propagation(44).is_private());
+        assert!(!propagation(42).is_private());
+
+        assert_eq!(mounts.get_mount_by_id(43).unwrap().propagate_from(), Some(1));
+        assert_eq!(
+            mounts.get_mount_by_id(43).unwrap().to_string(),
+            "43 28 8:6 /home/shared /srv/shared rw,noatime shared:20 master:1 propagate_from:1 - \
+             ext4 /dev/sda6 rw"
+        );
+
+        let groups = mounts.peer_groups();
+        assert_eq!(groups[&20].iter().map(|m| m.mount_id).collect::<Vec<_>>(), vec![42, 43]);
+        assert_eq!(mounts.peers(7).map(|m| m.mount_id).collect::<Vec<_>>(), vec![22]);
+        assert_eq!(mounts.slaves(1).map(|m| m.mount_id).collect::<Vec<_>>(), vec![40, 42, 43]);
+        assert_eq!(mounts.slave_peer_groups()[&1].iter().copied().collect::<Vec<_>>(), vec![20]);
+    }
+
+    #[test]
+    fn tree() {
+        let tree = MountTree::new(MountInfoList::parse_from(SAMPLE.lines()).unwrap());
+        let ids = |nodes: &mut dyn Iterator<Item = MountNode>| {
+            nodes.map(|node| node.mount_id).collect::<Vec<_>>()
+        };
+
+        assert_eq!(tree.len(), 9);
+        assert_eq!(ids(&mut tree.roots()), vec![28]);
+        assert_eq!(ids(&mut tree.get(28).unwrap().children()), vec![22, 23, 31, 40, 41, 43, 44]);
+        assert_eq!(ids(&mut tree.get(42).unwrap().ancestors()), vec![40, 28]);
+        assert_eq!(tree.get(42).unwrap().parent().unwrap().dest, PathBuf::from("/mnt/my data"));
+        assert_eq!(tree.get(42).unwrap().depth(), 2);
+        assert!(tree.get(28).unwrap().parent().is_none());
+
+        assert_eq!(ids(&mut tree.iter()), vec![28, 22, 23, 31, 40, 42, 41, 43, 44]);
+        assert_eq!(ids(&mut tree.submounts("/mnt/my data")), vec![40, 42]);
+        assert_eq!(ids(&mut tree.submounts("/mnt/my")), Vec::<u32>::new());
+
+        let cycle = "1 2 0:1 / /a rw - tmpfs a rw\n2 1 0:2 / /b rw - tmpfs b rw\n3 3 0:3 / / rw - \
+                     tmpfs c rw";
+        let tree = MountTree::new(MountInfoList::parse_from(cycle.lines()).unwrap());
+        assert_eq!(ids(&mut tree.roots()), vec![3]);
+        assert_eq!(ids(&mut tree.iter()), vec![3]);
+        assert_eq!(ids(&mut tree.get(1).unwrap().descendants()), vec![1, 2]);
+        assert_eq!(ids(&mut tree.submounts("/b")), vec![2, 1]);
+        assert_eq!(ids(&mut tree.get(1).unwrap().ancestors()), vec![2]);
+        assert_eq!(tree.get(1).unwrap().depth(), 1);
+    }
+
+    #[test]
+    fn non_utf8() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt, path::Path};
+
+        let line = b"50 28 8:17 /\xff /media/usb\\040\xff rw shared:30 - vfat /dev/sdb1 rw\n";
+        let mut entries = MountInfoIter::new_from_reader(&line[..]);
+        let entry = entries.next().unwrap().unwrap();
+        assert!(entries.next().is_none());
+        assert_eq!(entry.root, Path::new(OsStr::from_bytes(b"/\xff")));
+        assert_eq!(entry.dest, Path::new(OsStr::from_bytes(b"/media/usb \xff")));
+
+        let text = entry.to_string();
+        assert_eq!(text, "50 28 8:17 /\\377 /media/usb\\040\\377 rw shared:30 - vfat /dev/sdb1 rw");
+        assert_eq!(text.parse::<MountInfoEntry>().unwrap(), entry);
+
+        let mut bytes = Vec::new();
+        entry.write_bytes(&mut bytes).unwrap();
+        assert_eq!(bytes, &line[..line.len() - 1]);
+    }
+
+    #[test]
+    fn diff() {
+        let old = MountInfoList::parse_from(SAMPLE.lines()).unwrap();
+        let mut new = old.clone();
+        new.0[1].mount_options = "ro,nosuid,nodev,noexec,relatime".into();
+        new.0[5].dest = PathBuf::from("/run/user/1001");
+        new.0[8].fstype = "ramfs".into();
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.added, vec![new.0[8].clone()]);
+        assert_eq!(diff.removed, vec![old.0[8].clone()]);
+        assert_eq!(diff.remounted.len(), 1);
+        assert_eq!(diff.remounted[0].new.mount_id, 23);
+        assert_eq!(diff.moved.len(), 1);
+        assert_eq!(diff.moved[0].old.dest, PathBuf::from("/run/user/1000"));
+        assert!(new.diff(&new).is_empty());
+    }
+}
@@ -0,0 +1,125 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    io::{self, Error, ErrorKind},
+    str::FromStr,
+};
+
+/// A tag from the optional fields of a mountinfo entry.
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub enum OptionalField {
+    /// The mount is shared in the given peer group.
+    Shared(u32),
+    /// The mount is a slave to the given peer group.
+    Master(u32),
+    /// The mount is a slave which receives propagation from the given peer group, which is the
+    /// closest dominant peer group under the same root.
+    PropagateFrom(u32),
+    /// The mount is unbindable.
+    Unbindable,
+    /// A tag which is not known to this crate.
+    Unknown(String),
+}
+
+impl Display for OptionalField {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        match self {
+            OptionalField::Shared(id) => write!(fmt, "shared:{}", id),
+            OptionalField::Master(id) => write!(fmt, "master:{}", id),
+            OptionalField::PropagateFrom(id) => write!(fmt, "propagate_from:{}", id),
+            OptionalField::Unbindable => fmt.write_str("unbindable"),
+            OptionalField::Unknown(ref field) => fmt.write_str(field),
+        }
+    }
+}
+
+impl FromStr for OptionalField {
+    type Err = io::Error;
+
+    fn from_str(field: &str) -> Result<Self, Self::Err> {
+        let (tag, value) = match field.split_once(':') {
+            Some((tag, value)) => (tag, Some(value)),
+            None => (field, None),
+        };
+
+        let id = || {
+            value
+                .and_then(|value| value.parse::<u32>().ok())
+                .ok_or_else(|| Error::new(ErrorKind::InvalidData, "peer group ID is not a number"))
+        };
+
+        let field = match tag {
+            "shared" => OptionalField::Shared(id()?),
+            "master" => OptionalField::Master(id()?),
+            "propagate_from" => OptionalField::PropagateFrom(id()?),
+            "unbindable" if value.is_none() => OptionalField::Unbindable,
+            _ => OptionalField::Unknown(field.to_owned()),
+        };
+
+        Ok(field)
+    }
+}
+
+/// The propagation type of a mount, as derived from its optional fields.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq)]
+pub enum Propagation {
+    /// Mount and unmount events do not propagate to or from this mount.
+    Private,
+    /// Events propagate between this mount and the other members of its peer group.
+    Shared { peer_group: u32 },
+    /// Events propagate from the master peer group into this mount, but not the other way.
+    Slave { master: u32 },
+    /// The mount is both a slave of the master peer group, and shared within its own.
+    SharedAndSlave { peer_group: u32, master: u32 },
+    /// A private mount which may not be bind mounted.
+    Unbindable,
+}
+
+impl Propagation {
+    /// Derive the propagation type from the optional fields of a mountinfo entry.
+    pub fn from_fields(fields: &[OptionalField]) -> Self {
+        let mut peer_group = None;
+        let mut master = None;
+
+        for field in fields {
+            match *field {
+                OptionalField::Shared(id) => peer_group = Some(id),
+                OptionalField::Master(id) => master = Some(id),
+                OptionalField::Unbindable => return Propagation::Unbindable,
+                _ => (),
+            }
+        }
+
+        match (peer_group, master) {
+            (Some(peer_group), Some(master)) => Propagation::SharedAndSlave { peer_group, master },
+            (Some(peer_group), None) => Propagation::Shared { peer_group },
+            (None, Some(master)) => Propagation::Slave { master },
+            (None, None) => Propagation::Private,
+        }
+    }
+
+    /// Returns true if mount events do not propagate into this mount.
+    pub fn is_private(self) -> bool {
+        matches!(self, Propagation::Private | Propagation::Unbindable)
+    }
+
+    /// The peer group that this mount is shared in, if it is shared.
+    pub fn peer_group(self) -> Option<u32> {
+        match self {
+            Propagation::Shared { peer_group } | Propagation::SharedAndSlave { peer_group, .. } => {
+                Some(peer_group)
+            }
+            _ => None,
+        }
+    }
+
+    /// The peer group that this mount receives propagation from, if it is a slave.
+    pub fn master(self) -> Option<u32> {
+        match self {
+            Propagation::Slave { master } | Propagation::SharedAndSlave { master, .. } => {
+                Some(master)
+            }
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,435 @@
+use super::{MountInfoEntry, MountInfoList, OptionalField};
+use crate::{MountOptions, MountSource};
+use libc::c_long;
+use std::{
+    ffi::OsStr,
+    io::{self, Error, ErrorKind},
+    os::unix::ffi::OsStrExt,
+    path::PathBuf,
+    ptr, vec,
+};
+
+#[cfg(target_arch = "mips")]
+const SYSCALL_OFFSET: c_long = 4000;
+#[cfg(all(target_arch = "mips64", target_pointer_width = "64"))]
+const SYSCALL_OFFSET: c_long = 5000;
+#[cfg(all(target_arch = "mips64", target_pointer_width = "32"))]
+const SYSCALL_OFFSET: c_long = 6000;
+#[cfg(not(any(target_arch = "mips", target_arch = "mips64")))]
+const SYSCALL_OFFSET: c_long = 0;
+
+const SYS_STATMOUNT: c_long = SYSCALL_OFFSET + 457;
+const SYS_LISTMOUNT: c_long = SYSCALL_OFFSET + 458;
+
+/// Lists every mount in the mount namespace of the caller, when given to `listmount(2)`.
+const LSMT_ROOT: u64 = u64::MAX;
+const MNT_ID_REQ_SIZE_VER0: u32 = 24;
+
+const STATMOUNT_SB_BASIC: u64 = 0x1;
+const STATMOUNT_MNT_BASIC: u64 = 0x2;
+const STATMOUNT_PROPAGATE_FROM: u64 = 0x4;
+const STATMOUNT_MNT_ROOT: u64 = 0x8;
+const STATMOUNT_MNT_POINT: u64 = 0x10;
+const STATMOUNT_FS_TYPE: u64 = 0x20;
+const STATMOUNT_MNT_OPTS: u64 = 0x80;
+const STATMOUNT_FS_SUBTYPE: u64 = 0x100;
+const STATMOUNT_SB_SOURCE: u64 = 0x200;
+
+/// The fields which must be returned for a mountinfo entry to be built.
+const STATMOUNT_REQUIRED: u64 = STATMOUNT_SB_BASIC
+    | STATMOUNT_MNT_BASIC
+    | STATMOUNT_MNT_ROOT
+    | STATMOUNT_MNT_POINT
+    | STATMOUNT_FS_TYPE
+    | STATMOUNT_SB_SOURCE;
+
+const STATMOUNT_REQUEST: u64 =
+    STATMOUNT_REQUIRED | STATMOUNT_PROPAGATE_FROM | STATMOUNT_MNT_OPTS | STATMOUNT_FS_SUBTYPE;
+
+/// The size of `struct statmount`, after which its strings are stored.
+const STATMOUNT_SIZE: usize = 512;
+
+const MOUNT_ATTR_RDONLY: u64 = 0x1;
+const MOUNT_ATTR_NOSUID: u64 = 0x2;
+const MOUNT_ATTR_NODEV: u64 = 0x4;
+const MOUNT_ATTR_NOEXEC: u64 = 0x8;
+const MOUNT_ATTR__ATIME: u64 = 0x70;
+const MOUNT_ATTR_NOATIME: u64 = 0x10;
+const MOUNT_ATTR_NODIRATIME: u64 = 0x80;
+const MOUNT_ATTR_NOSYMFOLLOW: u64 = 0x0020_0000;
+
+const SB_RDONLY: u32 = 0x1;
+const SB_SYNCHRONOUS: u32 = 0x10;
+const SB_MANDLOCK: u32 = 0x40;
+const SB_DIRSYNC: u32 = 0x80;
+const SB_LAZYTIME: u32 = 0x0200_0000;
+
+const MS_UNBINDABLE: u64 = 1 << 17;
+const MS_SLAVE: u64 = 1 << 19;
+const MS_SHARED: u64 = 1 << 20;
+
+/// The argument of `listmount(2)` and `statmount(2)`, as `struct mnt_id_req`.
+#[repr(C)]
+struct MountIdRequest {
+    size:   u32,
+    spare:  u32,
+    mnt_id: u64,
+    param:  u64,
+}
+
+impl MountIdRequest {
+    fn new(mnt_id: u64, param: u64) -> Self {
+        MountIdRequest { size: MNT_ID_REQ_SIZE_VER0, spare: 0, mnt_id, param }
+    }
+}
+
+/// Call `listmount(2)` without room for any IDs, to learn whether the kernel supports it.
+fn probe() -> io::Result<()> {
+    let request = MountIdRequest::new(LSMT_ROOT, 0);
+    let result = unsafe {
+        libc::syscall(
+            SYS_LISTMOUNT,
+            &request as *const MountIdRequest,
+            ptr::null_mut::<u64>(),
+            0usize,
+            0,
+        )
+    };
+
+    if result < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// List the unique 64-bit IDs of every mount in the caller's mount namespace.
+fn list_mounts() -> io::Result<Vec<u64>> {
+    let mut ids = Vec::new();
+    let mut batch = vec![0u64; 1024];
+
+    loop {
+        // Each call continues after the last ID which was returned by the previous call.
+        let request = MountIdRequest::new(LSMT_ROOT, ids.last().copied().unwrap_or(0));
+        let read = unsafe {
+            libc::syscall(
+                SYS_LISTMOUNT,
+                &request as *const MountIdRequest,
+                batch.as_mut_ptr(),
+                batch.len(),
+                0,
+            )
+        };
+
+        if read < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let read = read as usize;
+        ids.extend_from_slice(&batch[..read]);
+        if read < batch.len() {
+            return Ok(ids);
+        }
+    }
+}
+
+/// Fetch the `struct statmount` of a mount into `buffer`, growing it as needed.
+fn stat_mount(id: u64, buffer: &mut Vec<u8>) -> io::Result<()> {
+    let request = MountIdRequest::new(id, STATMOUNT_REQUEST);
+
+    loop {
+        let result = unsafe {
+            libc::syscall(
+                SYS_STATMOUNT,
+                &request as *const MountIdRequest,
+                buffer.as_mut_ptr(),
+                buffer.len(),
+                0,
+            )
+        };
+
+        if result == 0 {
+            return Ok(());
+        }
+
+        match io::Error::last_os_error() {
+            why if why.raw_os_error() == Some(libc::EOVERFLOW) => {
+                let len = buffer.len() * 2;
+                buffer.resize(len, 0);
+            }
+            why => return Err(why),
+        }
+    }
+}
+
+fn u32_at(buffer: &[u8], offset: usize) -> u32 {
+    let mut bytes = [0; 4];
+    bytes.copy_from_slice(&buffer[offset..offset + 4]);
+    u32::from_ne_bytes(bytes)
+}
+
+fn u64_at(buffer: &[u8], offset: usize) -> u64 {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&buffer[offset..offset + 8]);
+    u64::from_ne_bytes(bytes)
+}
+
+/// Read a NUL-terminated string, whose position is stored at `offset` as an offset into the
+/// strings which follow the structure.
+fn string_at(buffer: &[u8], offset: usize) -> io::Result<&[u8]> {
+    let start = STATMOUNT_SIZE + u32_at(buffer, offset) as usize;
+    let string = buffer.get(start..).unwrap_or_default();
+    let end = string
+        .iter()
+        .position(|&byte| byte == 0)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "unterminated statmount string"))?;
+    Ok(&string[..end])
+}
+
+/// Build a mountinfo entry from a `struct statmount`, as the kernel would have written it in
+/// `/proc/self/mountinfo`.
+fn parse_statmount(buffer: &[u8]) -> io::Result<MountInfoEntry> {
+    if buffer.len() < STATMOUNT_SIZE {
+        return Err(Error::new(ErrorKind::InvalidData, "truncated statmount"));
+    }
+
+    let mask = u64_at(buffer, 8);
+    if mask & STATMOUNT_REQUIRED != STATMOUNT_REQUIRED {
+        return Err(Error::new(ErrorKind::Unsupported, "statmount lacks required fields"));
+    }
+
+    let string = |offset, flag| -> io::Result<&[u8]> {
+        if mask & flag == 0 {
+            return Ok(&[]);
+        }
+
+        string_at(buffer, offset)
+    };
+
+    let path = |offset, flag| -> io::Result<PathBuf> {
+        Ok(PathBuf::from(OsStr::from_bytes(string(offset, flag)?)))
+    };
+
+    let utf8 = |offset, flag| -> io::Result<&str> {
+        std::str::from_utf8(string(offset, flag)?)
+            .map_err(|_| Error::new(ErrorKind::InvalidData, "statmount string is not UTF-8"))
+    };
+
+    let attr = u64_at(buffer, 64);
+    let mut mount_options = MountOptions::new();
+    mount_options.push(if attr & MOUNT_ATTR_RDONLY != 0 { "ro" } else { "rw" });
+    for &(flag, name) in &[
+        (MOUNT_ATTR_NOSUID, "nosuid"),
+        (MOUNT_ATTR_NODEV, "nodev"),
+        (MOUNT_ATTR_NOEXEC, "noexec"),
+        (MOUNT_ATTR_NOATIME, "noatime"),
+        (MOUNT_ATTR_NODIRATIME, "nodiratime"),
+    ] {
+        if attr & flag != 0 {
+            mount_options.push(name);
+        }
+    }
+
+    if attr & MOUNT_ATTR__ATIME == 0 {
+        mount_options.push("relatime");
+    }
+
+    if attr & MOUNT_ATTR_NOSYMFOLLOW != 0 {
+        mount_options.push("nosymfollow");
+    }
+
+    let propagation = u64_at(buffer, 72);
+    let mut optional_fields = Vec::new();
+    if propagation & MS_SHARED != 0 {
+        optional_fields.push(OptionalField::Shared(u64_at(buffer, 80) as u32));
+    }
+
+    if propagation & MS_SLAVE != 0 {
+        let master = u64_at(buffer, 88);
+        optional_fields.push(OptionalField::Master(master as u32));
+
+        // As in mountinfo, the dominant peer group is only shown when it differs from the master.
+        let propagate_from = u64_at(buffer, 96);
+        if mask & STATMOUNT_PROPAGATE_FROM != 0 && propagate_from != 0 && propagate_from != master {
+            optional_fields.push(OptionalField::PropagateFrom(propagate_from as u32));
+        }
+    }
+
+    if propagation & MS_UNBINDABLE != 0 {
+        optional_fields.push(OptionalField::Unbindable);
+    }
+
+    let mut fstype = utf8(36, STATMOUNT_FS_TYPE)?.to_owned();
+    let subtype = utf8(120, STATMOUNT_FS_SUBTYPE)?;
+    if !subtype.is_empty() {
+        fstype.push('.');
+        fstype.push_str(subtype);
+    }
+
+    let sb_flags = u32_at(buffer, 32);
+    let mut super_options = MountOptions::new();
+    super_options.push(if sb_flags & SB_RDONLY != 0 { "ro" } else { "rw" });
+    for &(flag, name) in &[
+        (SB_SYNCHRONOUS, "sync"),
+        (SB_DIRSYNC, "dirsync"),
+        (SB_MANDLOCK, "mand"),
+        (SB_LAZYTIME, "lazytime"),
+    ] {
+        if sb_flags & flag != 0 {
+            super_options.push(name);
+        }
+    }
+
+    super_options.extend(MountOptions::from(utf8(4, STATMOUNT_MNT_OPTS)?));
+
+    let source = string(124, STATMOUNT_SB_SOURCE)?;
+    let source = if source.is_empty() { &b"none"[..] } else { source };
+
+    Ok(MountInfoEntry {
+        mount_id: u32_at(buffer, 56),
+        parent_id: u32_at(buffer, 60),
+        major: u32_at(buffer, 16),
+        minor: u32_at(buffer, 20),
+        root: path(104, STATMOUNT_MNT_ROOT)?,
+        dest: path(108, STATMOUNT_MNT_POINT)?,
+        mount_options,
+        optional_fields,
+        fstype,
+        source: MountSource::from(OsStr::from_bytes(source).to_owned()),
+        super_options,
+    })
+}
+
+/// Iteratively fetch the mounts of the caller's mount namespace with `listmount(2)` and
+/// `statmount(2)`, which are available since Linux 6.8.
+///
+/// Kernels older than 6.11 do not return the source of a mount, and are reported as
+/// unsupported.
+pub struct StatMountIter {
+    ids:    vec::IntoIter<u64>,
+    buffer: Vec<u8>,
+}
+
+impl StatMountIter {
+    pub fn new() -> io::Result<Self> {
+        Ok(Self { ids: list_mounts()?.into_iter(), buffer: vec![0; 4096] })
+    }
+}
+
+impl Iterator for StatMountIter {
+    type Item = io::Result<MountInfoEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let id = self.ids.next()?;
+            match stat_mount(id, &mut self.buffer) {
+                Ok(()) => return Some(parse_statmount(&self.buffer)),
+                // The mount was unmounted after it was listed.
+                Err(why) if why.raw_os_error() == Some(libc::ENOENT) => continue,
+                Err(why) => return Some(Err(why)),
+            }
+        }
+    }
+}
+
+/// Returns true if the error of the initial `probe` shows that the kernel does not support
+/// `listmount(2)`, or that it is blocked, such as by a seccomp filter.
+fn is_unsupported(why: &io::Error) -> bool {
+    matches!(why.raw_os_error(), Some(libc::ENOSYS) | Some(libc::EPERM) | Some(libc::EINVAL))
+}
+
+impl MountInfoList {
+    /// Read a new list of mounts into memory with `listmount(2)` and `statmount(2)`, which
+    /// avoids formatting and parsing text on systems with many mounts.
+    ///
+    /// Falls back to reading `/proc/self/mountinfo` on kernels which do not support them, or
+    /// which do not return every field of a mountinfo entry. Any other error of `statmount(2)`
+    /// is returned.
+    pub fn new_from_statmount() -> io::Result<MountInfoList> {
+        match probe() {
+            Ok(()) => (),
+            Err(why) if is_unsupported(&why) => return MountInfoList::new(),
+            Err(why) => return Err(why),
+        }
+
+        match StatMountIter::new().and_then(|iter| iter.collect::<io::Result<Vec<_>>>()) {
+            Ok(entries) => Ok(MountInfoList(entries)),
+            Err(why) if why.kind() == ErrorKind::Unsupported => MountInfoList::new(),
+            Err(why) => Err(why),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write a `struct statmount` with the given fields, and its strings.
+    fn statmount(fields: &[(usize, u64, usize)], strings: &[(usize, &[u8])]) -> Vec<u8> {
+        let mut buffer = vec![0; STATMOUNT_SIZE];
+        for &(offset, value, width) in fields {
+            buffer[offset..offset + width].copy_from_slice(&value.to_ne_bytes()[..width]);
+        }
+
+        for &(offset, string) in strings {
+            let position = (buffer.len() - STATMOUNT_SIZE) as u32;
+            buffer[offset..offset + 4].copy_from_slice(&position.to_ne_bytes());
+            buffer.extend_from_slice(string);
+            buffer.push(0);
+        }
+
+        buffer
+    }
+
+    #[test]
+    fn statmount_entry() {
+        let mask = STATMOUNT_REQUEST;
+        let buffer = statmount(
+            &[
+                (8, mask, 8),
+                (16, 8, 4),
+                (20, 6, 4),
+                (32, 0, 4),
+                (56, 42, 4),
+                (60, 40, 4),
+                (64, MOUNT_ATTR_NOSUID | MOUNT_ATTR_NOATIME, 8),
+                (72, MS_SHARED | MS_SLAVE, 8),
+                (80, 20, 8),
+                (88, 1, 8),
+                (96, 1, 8),
+            ],
+            &[
+                (4, b"errors=remount-ro"),
+                (36, b"ext4"),
+                (104, b"/home/shared"),
+                (108, b"/mnt/my data"),
+                (120, b""),
+                (124, b"/dev/sda6"),
+            ],
+        );
+
+        let entry = parse_statmount(&buffer).unwrap();
+        let expected = "42 40 8:6 /home/shared /mnt/my\\040data rw,nosuid,noatime shared:20 \
+                        master:1 - ext4 /dev/sda6 rw,errors=remount-ro";
+        assert_eq!(entry.to_string(), expected);
+        assert_eq!(entry, expected.parse::<MountInfoEntry>().unwrap());
+
+        let buffer = statmount(&[(8, mask & !STATMOUNT_SB_SOURCE, 8)], &[]);
+        assert_eq!(parse_statmount(&buffer).unwrap_err().kind(), ErrorKind::Unsupported);
+        assert_eq!(parse_statmount(&[0; 64]).unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn fallback() {
+        assert!(is_unsupported(&Error::from_raw_os_error(libc::ENOSYS)));
+        assert!(is_unsupported(&Error::from_raw_os_error(libc::EINVAL)));
+        assert!(!is_unsupported(&Error::from_raw_os_error(libc::EFAULT)));
+
+        if let Err(why) = probe() {
+            assert!(is_unsupported(&why), "unexpected error: {}", why);
+        }
+
+        let mounts = MountInfoList::new_from_statmount().unwrap();
+        assert!(mounts.0.iter().any(|mount| mount.dest == std::path::Path::new("/")));
+    }
+}
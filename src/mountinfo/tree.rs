@@ -0,0 +1,168 @@
+use super::{MountInfoEntry, MountInfoList};
+use std::{collections::HashMap, ops::Deref, path::Path};
+
+/// A tree of mounts, reconstructed from the mount and parent IDs of mountinfo entries.
+///
+/// Children are kept in the order that they appear in the mountinfo file, which is the order
+/// in which they were mounted.
+#[derive(Debug, Default, Clone)]
+pub struct MountTree {
+    entries:  Vec<MountInfoEntry>,
+    parents:  Vec<Option<usize>>,
+    children: Vec<Vec<usize>>,
+    roots:    Vec<usize>,
+    ids:      HashMap<u32, usize>,
+}
+
+impl MountTree {
+    /// Reconstruct the mount tree from a list of mountinfo entries.
+    ///
+    /// Entries whose parent is not in the list, or which are their own parent, become roots.
+    pub fn new(list: MountInfoList) -> Self {
+        let entries = list.0;
+
+        let ids: HashMap<u32, usize> =
+            entries.iter().enumerate().map(|(index, entry)| (entry.mount_id, index)).collect();
+
+        let mut parents = vec![None; entries.len()];
+        let mut children = vec![Vec::new(); entries.len()];
+        let mut roots = Vec::new();
+
+        for (index, entry) in entries.iter().enumerate() {
+            match ids.get(&entry.parent_id) {
+                Some(&parent) if entry.parent_id != entry.mount_id => {
+                    parents[index] = Some(parent);
+                    children[parent].push(index);
+                }
+                _ => roots.push(index),
+            }
+        }
+
+        Self { entries, parents, children, roots, ids }
+    }
+
+    /// Iterate through each mount which has no parent in the tree.
+    pub fn roots(&self) -> impl Iterator<Item = MountNode<'_>> + '_ {
+        self.roots.iter().map(move |&index| MountNode { tree: self, index })
+    }
+
+    /// Find the node of the mount with the given mount ID.
+    pub fn get(&self, mount_id: u32) -> Option<MountNode<'_>> {
+        self.ids.get(&mount_id).map(|&index| MountNode { tree: self, index })
+    }
+
+    /// Find the node of the visible mount at the `path` destination.
+    pub fn get_by_dest<P: AsRef<Path>>(&self, path: P) -> Option<MountNode<'_>> {
+        self.entries
+            .iter()
+            .rposition(|entry| entry.dest == path.as_ref())
+            .map(|index| MountNode { tree: self, index })
+    }
+
+    /// Traverse the whole tree depth-first, visiting parents before their children.
+    pub fn iter(&self) -> DepthFirst<'_> {
+        DepthFirst::new(self, self.roots.iter().rev().copied().collect())
+    }
+
+    /// Traverse every mount at or beneath the `path` destination depth-first.
+    ///
+    /// Unmounting the mounts in the reverse of this order will never unmount a parent before
+    /// its children.
+    pub fn submounts<P: AsRef<Path>>(&self, path: P) -> DepthFirst<'_> {
+        let stack = self.get_by_dest(path).map_or_else(Vec::new, |node| vec![node.index]);
+        DepthFirst::new(self, stack)
+    }
+
+    /// The number of mounts in the tree.
+    pub fn len(&self) -> usize { self.entries.len() }
+
+    /// Returns true if the tree contains no mounts.
+    pub fn is_empty(&self) -> bool { self.entries.is_empty() }
+}
+
+impl From<MountInfoList> for MountTree {
+    fn from(list: MountInfoList) -> Self { Self::new(list) }
+}
+
+/// A reference to a mount within a `MountTree`.
+#[derive(Debug, Copy, Clone)]
+pub struct MountNode<'a> {
+    tree:  &'a MountTree,
+    index: usize,
+}
+
+impl<'a> MountNode<'a> {
+    /// The mount entry of this node.
+    pub fn entry(self) -> &'a MountInfoEntry { &self.tree.entries[self.index] }
+
+    /// The mount which this mount is mounted on, if it is in the tree.
+    pub fn parent(self) -> Option<MountNode<'a>> {
+        self.tree.parents[self.index].map(|index| MountNode { tree: self.tree, index })
+    }
+
+    /// Iterate through the mounts which are mounted directly on this mount.
+    pub fn children(self) -> impl Iterator<Item = MountNode<'a>> {
+        let tree = self.tree;
+        tree.children[self.index].iter().map(move |&index| MountNode { tree, index })
+    }
+
+    /// Iterate through each parent of this mount, from the nearest to the root.
+    pub fn ancestors(self) -> impl Iterator<Item = MountNode<'a>> {
+        let mut current = self;
+        let mut visited = vec![false; self.tree.len()];
+        visited[self.index] = true;
+
+        // Stops at the first repeated mount, in case the parent IDs of the input contained a cycle.
+        std::iter::from_fn(move || {
+            current = current.parent()?;
+            if std::mem::replace(&mut visited[current.index], true) {
+                return None;
+            }
+
+            Some(current)
+        })
+    }
+
+    /// Traverse this mount and every mount beneath it depth-first.
+    pub fn descendants(self) -> DepthFirst<'a> { DepthFirst::new(self.tree, vec![self.index]) }
+
+    /// The number of ancestors that this mount has.
+    pub fn depth(self) -> usize { self.ancestors().count() }
+}
+
+impl<'a> Deref for MountNode<'a> {
+    type Target = MountInfoEntry;
+
+    fn deref(&self) -> &Self::Target { self.entry() }
+}
+
+/// A depth-first traversal through a `MountTree`, which visits parents before their children.
+#[derive(Debug, Clone)]
+pub struct DepthFirst<'a> {
+    tree:    &'a MountTree,
+    stack:   Vec<usize>,
+    visited: Vec<bool>,
+}
+
+impl<'a> DepthFirst<'a> {
+    fn new(tree: &'a MountTree, stack: Vec<usize>) -> Self {
+        DepthFirst { tree, stack, visited: vec![false; tree.len()] }
+    }
+}
+
+impl<'a> Iterator for DepthFirst<'a> {
+    type Item = MountNode<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Each mount is visited once, in case the parent IDs of the input contained a cycle.
+        let index = loop {
+            let index = self.stack.pop()?;
+            if !std::mem::replace(&mut self.visited[index], true) {
+                break index;
+            }
+        };
+
+        self.stack.extend(self.tree.children[index].iter().rev());
+        Some(MountNode { tree: self.tree, index })
+    }
+}
@@ -0,0 +1,125 @@
+use super::{MountInfo, MountList, MountSource};
+use std::{collections::HashMap, hash::Hash};
+
+/// An entry which exists in both lists, but which differs between them.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub struct Change<T> {
+    pub old: T,
+    pub new: T,
+}
+
+/// The differences between two lists of mounts.
+///
+/// Entries which are identical in both lists are not reported.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub struct MountDiff<T> {
+    /// Mounts which only exist in the new list.
+    pub added:     Vec<T>,
+    /// Mounts which only exist in the old list.
+    pub removed:   Vec<T>,
+    /// Mounts which remain at the same destination, but whose options have changed.
+    pub remounted: Vec<Change<T>>,
+    /// Mounts which are now at a different destination.
+    pub moved:     Vec<Change<T>>,
+}
+
+impl<T> MountDiff<T> {
+    /// Returns true if the lists are identical.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.remounted.is_empty()
+            && self.moved.is_empty()
+    }
+}
+
+impl<T> Default for MountDiff<T> {
+    fn default() -> Self {
+        MountDiff {
+            added:     Vec::new(),
+            removed:   Vec::new(),
+            remounted: Vec::new(),
+            moved:     Vec::new(),
+        }
+    }
+}
+
+impl MountList {
+    /// Compare this list with a newer list of mounts.
+    ///
+    /// Mounts are keyed by their destination. A mount is moved if its source and file system type
+    /// reappear at another destination, which is never assumed of pseudo sources such as `tmpfs`.
+    pub fn diff(&self, new: &MountList) -> MountDiff<MountInfo> {
+        let (mut old, mut new) = unmatched(&self.0, &new.0);
+
+        let same_fs =
+            |old: &MountInfo, new: &MountInfo| old.source == new.source && old.fstype == new.fstype;
+
+        let remounted =
+            take_changes(&mut old, &mut new, |old, new| old.dest == new.dest && same_fs(old, new));
+
+        let moved = take_changes(&mut old, &mut new, |old, new| {
+            !matches!(old.source, MountSource::Pseudo(_)) && same_fs(old, new)
+        });
+
+        MountDiff {
+            added: new.into_iter().cloned().collect(),
+            removed: old.into_iter().cloned().collect(),
+            remounted,
+            moved,
+        }
+    }
+}
+
+/// The entries of each list which are not found in the other, in their original order.
+///
+/// Identical entries may appear more than once, such as mounts stacked on the same destination,
+/// so they are counted rather than merely looked up.
+pub(crate) fn unmatched<'a, T: Eq + Hash>(old: &'a [T], new: &'a [T]) -> (Vec<&'a T>, Vec<&'a T>) {
+    fn count<T: Eq + Hash>(entries: &[T]) -> HashMap<&T, usize> {
+        let mut counts = HashMap::new();
+        for entry in entries {
+            *counts.entry(entry).or_insert(0) += 1;
+        }
+
+        counts
+    }
+
+    fn filter<'a, T: Eq + Hash>(entries: &'a [T], mut counts: HashMap<&'a T, usize>) -> Vec<&'a T> {
+        entries
+            .iter()
+            .filter(|entry| match counts.get_mut(entry) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    false
+                }
+                _ => true,
+            })
+            .collect()
+    }
+
+    (filter(old, count(new)), filter(new, count(old)))
+}
+
+/// Pair up the entries of both lists which match, and remove them from the lists.
+pub(crate) fn take_changes<T: Clone, F: Fn(&T, &T) -> bool>(
+    old: &mut Vec<&T>,
+    new: &mut Vec<&T>,
+    matches: F,
+) -> Vec<Change<T>> {
+    let mut changes = Vec::new();
+    let mut index = 0;
+    while index < new.len() {
+        match old.iter().position(|old| matches(old, new[index])) {
+            Some(position) => {
+                let old = old.remove(position).clone();
+                changes.push(Change { old, new: new.remove(index).clone() });
+            }
+            None => index += 1,
+        }
+    }
+
+    changes
+}
@@ -1,30 +1,33 @@
-use partition_identity::PartitionID;
+use super::{MountOptions, MountSource};
+use crate::{
+    escape::{self, Escaped},
+    parse,
+};
 use std::{
-    char,
-    ffi::OsString,
     fmt::{self, Display, Formatter},
-    io::{self, Error, ErrorKind},
-    os::unix::ffi::OsStringExt,
+    io,
     path::PathBuf,
     str::FromStr,
 };
 
 /// A mount entry which contains information regarding how and where a source
 /// is mounted.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Default, Clone, Hash, Eq, PartialEq)]
 pub struct MountInfo {
     /// The source which is mounted.
-    pub source: PathBuf,
+    pub source:  MountSource,
     /// Where the source is mounted.
-    pub dest: PathBuf,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::os_string"))]
+    pub dest:    PathBuf,
     /// The type of the mounted file system.
-    pub fstype: String,
+    pub fstype:  String,
     /// Options specified for this file system.
-    pub options: Vec<String>,
+    pub options: MountOptions,
     /// Defines if the file system should be dumped.
-    pub dump: i32,
+    pub dump:    i32,
     /// Defines if the file system should be checked, and in what order.
-    pub pass: i32,
+    pub pass:    i32,
 }
 
 impl Display for MountInfo {
@@ -32,10 +35,10 @@ impl Display for MountInfo {
         write!(
             fmt,
             "{} {} {} {} {} {}",
-            self.source.display(),
-            self.dest.display(),
+            self.source,
+            Escaped(self.dest.as_os_str()),
             self.fstype,
-            if self.options.is_empty() { "defaults".into() } else { self.options.join(",") },
+            self.options,
             self.dump,
             self.pass
         )
@@ -45,88 +48,57 @@ impl Display for MountInfo {
 impl FromStr for MountInfo {
     type Err = io::Error;
 
-    fn from_str(line: &str) -> Result<Self, Self::Err> {
-        let mut parts = line.split_whitespace();
-
-        fn map_err(why: &'static str) -> io::Error { Error::new(ErrorKind::InvalidData, why) }
-
-        let source = parts.next().ok_or_else(|| map_err("missing source"))?;
-        let dest = parts.next().ok_or_else(|| map_err("missing dest"))?;
-        let fstype = parts.next().ok_or_else(|| map_err("missing type"))?;
-        let options = parts.next().ok_or_else(|| map_err("missing options"))?;
-
-        let dump = parts.next().map_or(Ok(0), |value| {
-            value.parse::<i32>().map_err(|_| map_err("dump value is not a number"))
-        })?;
+    fn from_str(line: &str) -> Result<Self, Self::Err> { Self::from_bytes(line.as_bytes()) }
+}
 
-        let pass = parts.next().map_or(Ok(0), |value| {
-            value.parse::<i32>().map_err(|_| map_err("pass value is not a number"))
-        })?;
+impl MountInfo {
+    /// Attempt to parse a `/proc/mounts`-like line.
+    #[deprecated]
+    pub fn parse_line(line: &str) -> io::Result<MountInfo> { line.parse::<Self>() }
 
-        let path = Self::parse_value(source)?;
-        let path = path.to_str().ok_or_else(|| map_err("non-utf8 paths are unsupported"))?;
+    /// Parse a `/proc/mounts`-like line.
+    ///
+    /// The source and dest fields may contain any bytes, including those which are not valid
+    /// UTF-8.
+    pub fn from_bytes(line: &[u8]) -> io::Result<Self> {
+        let line = parse::Line(line);
+        let mut parts = line.fields();
 
-        let source = if path.starts_with("/dev/disk/by-") {
-            Self::fetch_from_disk_by_path(path)?
-        } else {
-            PathBuf::from(path)
-        };
+        let source = line.next(&mut parts, "source")?;
+        let dest = line.next(&mut parts, "dest")?;
+        let fstype = line.next(&mut parts, "type")?;
+        let options = line.next(&mut parts, "options")?;
 
-        let path = Self::parse_value(dest)?;
-        let path = path.to_str().ok_or_else(|| map_err("non-utf8 paths are unsupported"))?;
+        let dump = parts
+            .next()
+            .map_or(Ok(0), |value| line.number(value, "dump", "dump value is not a number"))?;
 
-        let dest = PathBuf::from(path);
+        let pass = parts
+            .next()
+            .map_or(Ok(0), |value| line.number(value, "pass", "pass value is not a number"))?;
 
         Ok(MountInfo {
-            source,
-            dest,
-            fstype: fstype.to_owned(),
-            options: options.split(',').map(String::from).collect(),
+            source: MountSource::from(line.decode(source, "source")?),
+            dest: PathBuf::from(line.decode(dest, "dest")?),
+            fstype: line.utf8(fstype, "type", "type is not UTF-8")?.to_owned(),
+            options: line.utf8(options, "options", "options are not UTF-8")?.into(),
             dump,
             pass,
         })
     }
-}
-
-impl MountInfo {
-    /// Attempt to parse a `/proc/mounts`-like line.
-    #[deprecated]
-    pub fn parse_line(line: &str) -> io::Result<MountInfo> { line.parse::<Self>() }
-
-    fn fetch_from_disk_by_path(path: &str) -> io::Result<PathBuf> {
-        PartitionID::from_disk_by_path(path)
-            .map_err(|why| Error::new(ErrorKind::InvalidData, format!("{}: {}", path, why)))?
-            .get_device_path()
-            .ok_or_else(|| {
-                Error::new(ErrorKind::NotFound, format!("device path for {} was not found", path))
-            })
-    }
-
-    fn parse_value(value: &str) -> io::Result<OsString> {
-        let mut ret = Vec::new();
-
-        let mut bytes = value.bytes();
-        while let Some(b) = bytes.next() {
-            match b {
-                b'\\' => {
-                    let mut code = 0;
-                    for _i in 0..3 {
-                        if let Some(b) = bytes.next() {
-                            code *= 8;
-                            code += u32::from_str_radix(&(b as char).to_string(), 8)
-                                .map_err(|err| Error::new(ErrorKind::Other, err))?;
-                        } else {
-                            return Err(Error::new(ErrorKind::Other, "truncated octal code"));
-                        }
-                    }
-                    ret.push(code as u8);
-                }
-                _ => {
-                    ret.push(b);
-                }
-            }
-        }
 
-        Ok(OsString::from_vec(ret))
+    /// Write the entry as a `/proc/mounts`-like line, without a trailing newline.
+    ///
+    /// Unlike `Display`, bytes which are not valid UTF-8 are written as they are.
+    pub fn write_bytes<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut line = Vec::with_capacity(128);
+        self.source.encode(&mut line);
+        line.push(b' ');
+        escape::encode(self.dest.as_os_str(), &mut line);
+        line.extend_from_slice(
+            format!(" {} {} {} {}", self.fstype, self.options, self.dump, self.pass).as_bytes(),
+        );
+
+        writer.write_all(&line)
     }
 }
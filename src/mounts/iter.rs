@@ -1,15 +1,18 @@
 use super::MountInfo;
+use crate::{error, namespace, parse};
 use std::{
     fs::File,
     io::{self, BufRead, BufReader},
-    path::Path,
-    str::FromStr,
+    os::unix::io::AsRawFd,
+    path::{Path, PathBuf},
 };
 
 /// Iteratively parse the `/proc/mounts` file.
 pub struct MountIter<R> {
     file:   R,
-    buffer: String,
+    buffer: Vec<u8>,
+    line:   usize,
+    path:   Option<PathBuf>,
 }
 
 impl MountIter<BufReader<File>> {
@@ -17,14 +20,39 @@ impl MountIter<BufReader<File>> {
 
     /// Read mounts from any mount-tab-like file.
     pub fn new_from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
-        Ok(Self::new_from_reader(BufReader::new(File::open(path)?)))
+        let path = path.as_ref();
+        let mut iter = Self::new_from_reader(BufReader::new(File::open(path)?));
+        iter.path = Some(path.to_path_buf());
+        Ok(iter)
+    }
+
+    /// Read the mounts of the process with the given PID, from `/proc/<pid>/mounts`.
+    pub fn new_from_pid(pid: u32) -> io::Result<Self> {
+        Self::new_from_file(format!("/proc/{}/mounts", pid))
+    }
+
+    /// Read the mounts of the process referred to by a pidfd.
+    pub fn new_from_pidfd<F: AsRawFd>(pidfd: &F) -> io::Result<Self> {
+        let (file, path) = namespace::open_pidfd_file(pidfd.as_raw_fd(), "mounts")?;
+        let mut iter = Self::new_from_reader(BufReader::new(file));
+        iter.path = Some(path);
+        Ok(iter)
+    }
+
+    /// Read the mounts of the mount namespace referred to by a namespace file descriptor, such
+    /// as an opened `/proc/<pid>/ns/mnt`, through a process which is a member of it.
+    pub fn new_from_namespace<F: AsRawFd>(ns: &F) -> io::Result<Self> {
+        let (file, path) = namespace::open_namespace_file(ns.as_raw_fd(), "mounts")?;
+        let mut iter = Self::new_from_reader(BufReader::new(file));
+        iter.path = Some(path);
+        Ok(iter)
     }
 }
 
 impl<R: BufRead> MountIter<R> {
     /// Read mounts from any in-memory buffer.
     pub fn new_from_reader(readable: R) -> Self {
-        Self { file: readable, buffer: String::with_capacity(512) }
+        Self { file: readable, buffer: Vec::with_capacity(512), line: 0, path: None }
     }
 
     /// Iterator-based variant of `source_mounted_at`.
@@ -60,12 +88,16 @@ impl<R: BufRead> Iterator for MountIter<R> {
     fn next(&mut self) -> Option<Self::Item> {
         loop {
             self.buffer.clear();
-            match self.file.read_line(&mut self.buffer) {
+            match self.file.read_until(b'\n', &mut self.buffer) {
                 Ok(read) if read == 0 => return None,
                 Ok(_) => {
-                    let line = self.buffer.trim_start();
-                    if !(line.starts_with('#') || line.is_empty()) {
-                        return Some(MountInfo::from_str(line));
+                    self.line += 1;
+                    let line = parse::trim_start(&self.buffer);
+                    if !(line.starts_with(b"#") || line.is_empty()) {
+                        let result = MountInfo::from_bytes(&self.buffer);
+                        return Some(result.map_err(|why| {
+                            error::locate(why, Some(self.line), self.path.as_deref())
+                        }));
                     }
                 }
                 Err(why) => return Some(Err(why)),
@@ -0,0 +1,214 @@
+use super::{MountOption, MountOptions};
+use libc::c_ulong;
+
+/// The options of a mount, split into the arguments that `mount(2)` expects.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Default, Clone, Hash, Eq, PartialEq)]
+pub struct KernelMountOptions {
+    /// The `MS_*` flags of the mount.
+    pub flags:     c_ulong,
+    /// The comma-separated options which are specific to the file system.
+    pub data:      String,
+    /// Options which are only interpreted by userspace, such as `noauto` or `x-*`, and which
+    /// are never passed to the kernel.
+    pub userspace: MountOptions,
+}
+
+/// The options which set or clear `MS_*` flags, along with the flags that they set and clear.
+const KERNEL_FLAGS: &[(&str, c_ulong, c_ulong)] = &[
+    ("ro", libc::MS_RDONLY, 0),
+    ("rw", 0, libc::MS_RDONLY),
+    ("suid", 0, libc::MS_NOSUID),
+    ("nosuid", libc::MS_NOSUID, 0),
+    ("dev", 0, libc::MS_NODEV),
+    ("nodev", libc::MS_NODEV, 0),
+    ("exec", 0, libc::MS_NOEXEC),
+    ("noexec", libc::MS_NOEXEC, 0),
+    ("sync", libc::MS_SYNCHRONOUS, 0),
+    ("async", 0, libc::MS_SYNCHRONOUS),
+    ("dirsync", libc::MS_DIRSYNC, 0),
+    ("mand", libc::MS_MANDLOCK, 0),
+    ("nomand", 0, libc::MS_MANDLOCK),
+    ("atime", 0, libc::MS_NOATIME),
+    ("noatime", libc::MS_NOATIME, libc::MS_RELATIME | libc::MS_STRICTATIME),
+    ("relatime", libc::MS_RELATIME, libc::MS_NOATIME | libc::MS_STRICTATIME),
+    ("norelatime", 0, libc::MS_RELATIME),
+    ("strictatime", libc::MS_STRICTATIME, libc::MS_NOATIME | libc::MS_RELATIME),
+    ("nostrictatime", 0, libc::MS_STRICTATIME),
+    ("diratime", 0, libc::MS_NODIRATIME),
+    ("nodiratime", libc::MS_NODIRATIME, 0),
+    ("lazytime", libc::MS_LAZYTIME, 0),
+    ("nolazytime", 0, libc::MS_LAZYTIME),
+    ("silent", libc::MS_SILENT, 0),
+    ("loud", 0, libc::MS_SILENT),
+    ("iversion", libc::MS_I_VERSION, 0),
+    ("noiversion", 0, libc::MS_I_VERSION),
+    ("remount", libc::MS_REMOUNT, 0),
+    ("move", libc::MS_MOVE, 0),
+    ("bind", libc::MS_BIND, 0),
+    ("rbind", libc::MS_BIND | libc::MS_REC, 0),
+    ("private", libc::MS_PRIVATE, 0),
+    ("rprivate", libc::MS_PRIVATE | libc::MS_REC, 0),
+    ("shared", libc::MS_SHARED, 0),
+    ("rshared", libc::MS_SHARED | libc::MS_REC, 0),
+    ("slave", libc::MS_SLAVE, 0),
+    ("rslave", libc::MS_SLAVE | libc::MS_REC, 0),
+    ("unbindable", libc::MS_UNBINDABLE, 0),
+    ("runbindable", libc::MS_UNBINDABLE | libc::MS_REC, 0),
+];
+
+/// The flags which are written back as options, in the order that they are written.
+///
+/// Flags which may be combined with `MS_REC` are written with an `r` prefix when it is set.
+const FLAG_NAMES: &[(c_ulong, &str)] = &[
+    (libc::MS_NOSUID, "nosuid"),
+    (libc::MS_NODEV, "nodev"),
+    (libc::MS_NOEXEC, "noexec"),
+    (libc::MS_SYNCHRONOUS, "sync"),
+    (libc::MS_DIRSYNC, "dirsync"),
+    (libc::MS_MANDLOCK, "mand"),
+    (libc::MS_NOATIME, "noatime"),
+    (libc::MS_NODIRATIME, "nodiratime"),
+    (libc::MS_RELATIME, "relatime"),
+    (libc::MS_STRICTATIME, "strictatime"),
+    (libc::MS_LAZYTIME, "lazytime"),
+    (libc::MS_SILENT, "silent"),
+    (libc::MS_I_VERSION, "iversion"),
+    (libc::MS_REMOUNT, "remount"),
+    (libc::MS_MOVE, "move"),
+];
+
+const RECURSIVE_FLAG_NAMES: &[(c_ulong, &str)] = &[
+    (libc::MS_BIND, "bind"),
+    (libc::MS_PRIVATE, "private"),
+    (libc::MS_SHARED, "shared"),
+    (libc::MS_SLAVE, "slave"),
+    (libc::MS_UNBINDABLE, "unbindable"),
+];
+
+impl MountOption {
+    /// Returns true if the option is only interpreted by userspace tools such as `mount(8)`,
+    /// and is not passed to the kernel.
+    pub fn is_userspace(&self) -> bool {
+        match self.key.as_str() {
+            "defaults" | "auto" | "noauto" | "nofail" | "user" | "nouser" | "users" | "owner"
+            | "group" | "_netdev" | "comment" => true,
+            key => key.starts_with("x-"),
+        }
+    }
+}
+
+impl MountOptions {
+    /// Split the options into the `MS_*` flags and file system data of `mount(2)`.
+    ///
+    /// Later flags override earlier ones, and options which are neither flags nor userspace
+    /// options are passed through as data, in order.
+    pub fn to_kernel(&self) -> KernelMountOptions {
+        let mut kernel = KernelMountOptions::default();
+
+        for option in self {
+            if option.is_userspace() {
+                kernel.userspace.push(option.clone());
+                continue;
+            }
+
+            let flags = match option.value {
+                Some(_) => None,
+                None => KERNEL_FLAGS.iter().find(|(name, ..)| *name == option.key),
+            };
+
+            match flags {
+                Some(&(_, set, clear)) => kernel.flags = (kernel.flags & !clear) | set,
+                None => {
+                    if !kernel.data.is_empty() {
+                        kernel.data.push(',');
+                    }
+
+                    kernel.data.push_str(&option.to_string());
+                }
+            }
+        }
+
+        kernel
+    }
+}
+
+impl KernelMountOptions {
+    /// Options with the given `MS_*` flags and file system data, and no userspace options.
+    pub fn new(flags: c_ulong, data: &str) -> Self {
+        KernelMountOptions { flags, data: data.into(), userspace: MountOptions::new() }
+    }
+
+    /// Convert the flags and data back into mount options.
+    ///
+    /// `ro` or `rw` is always written first, followed by the remaining flags, the file system
+    /// data, and finally the userspace options.
+    pub fn to_options(&self) -> MountOptions {
+        let mut options = MountOptions::new();
+        options.push(if self.flags & libc::MS_RDONLY != 0 { "ro" } else { "rw" });
+
+        for &(flag, name) in FLAG_NAMES {
+            if self.flags & flag != 0 {
+                options.push(name);
+            }
+        }
+
+        let recursive = self.flags & libc::MS_REC != 0;
+        for &(flag, name) in RECURSIVE_FLAG_NAMES {
+            if self.flags & flag != 0 {
+                let name = if recursive { format!("r{}", name) } else { name.to_owned() };
+                options.push(name.as_str());
+            }
+        }
+
+        options.extend(MountOptions::from(self.data.as_str()));
+        options.extend(self.userspace.iter().cloned());
+        options
+    }
+}
+
+impl<'a> From<&'a MountOptions> for KernelMountOptions {
+    fn from(options: &'a MountOptions) -> Self { options.to_kernel() }
+}
+
+impl<'a> From<&'a KernelMountOptions> for MountOptions {
+    fn from(kernel: &'a KernelMountOptions) -> Self { kernel.to_options() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{super::options::FLAGS, *};
+
+    #[test]
+    fn kernel_options() {
+        let options = MountOptions::from(
+            "defaults,noauto,ro,nosuid,relatime,noatime,size=10%,x-systemd.automount,uid=1000,\
+             comment=foo,rw,_netdev",
+        );
+
+        let kernel = options.to_kernel();
+        assert_eq!(kernel.flags, libc::MS_NOSUID | libc::MS_NOATIME);
+        assert_eq!(kernel.data, "size=10%,uid=1000");
+        assert_eq!(
+            kernel.userspace.to_string(),
+            "defaults,noauto,x-systemd.automount,comment=foo,_netdev"
+        );
+
+        assert_eq!(
+            kernel.to_options().to_string(),
+            "rw,nosuid,noatime,size=10%,uid=1000,defaults,noauto,x-systemd.automount,comment=foo,\
+             _netdev"
+        );
+
+        let kernel = KernelMountOptions::new(libc::MS_RDONLY | libc::MS_BIND | libc::MS_REC, "");
+        assert_eq!(kernel.to_options().to_string(), "ro,rbind");
+        assert_eq!(kernel.to_options().to_kernel(), kernel);
+    }
+
+    #[test]
+    fn every_flag_has_kernel_bits() {
+        for (_, name) in FLAGS {
+            assert!(KERNEL_FLAGS.iter().any(|(kernel, ..)| kernel == name), "{} is missing", name);
+        }
+    }
+}
@@ -1,19 +1,21 @@
 use super::{MountInfo, MountIter};
+use crate::parse;
 use std::{
+    fs,
     io::{self, BufRead},
-    os::unix::ffi::OsStrExt,
+    os::unix::{ffi::OsStrExt, io::AsRawFd},
     path::Path,
-    str::FromStr,
 };
 
 /// A list of parsed mount entries from `/proc/mounts`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Default, Clone, Hash, Eq, PartialEq)]
 pub struct MountList(pub Vec<MountInfo>);
 
 impl MountList {
     /// Parse mounts given from an iterator of mount entry lines.
     pub fn parse_from<'a, I: Iterator<Item = &'a str>>(lines: I) -> io::Result<MountList> {
-        lines.map(MountInfo::from_str).collect::<io::Result<Vec<MountInfo>>>().map(MountList)
+        parse::lines(lines, MountInfo::from_bytes).map(MountList)
     }
 
     /// Read a new list of mounts into memory from `/proc/mounts`.
@@ -26,6 +28,24 @@ impl MountList {
         Ok(MountList(MountIter::new_from_file(path)?.collect::<io::Result<Vec<MountInfo>>>()?))
     }
 
+    /// Read a new list of mounts into memory from `/proc/<pid>/mounts`.
+    pub fn new_from_pid(pid: u32) -> io::Result<MountList> {
+        Ok(MountList(MountIter::new_from_pid(pid)?.collect::<io::Result<Vec<MountInfo>>>()?))
+    }
+
+    /// Read a new list of mounts into memory from the process referred to by a pidfd.
+    pub fn new_from_pidfd<F: AsRawFd>(pidfd: &F) -> io::Result<MountList> {
+        Ok(MountList(MountIter::new_from_pidfd(pidfd)?.collect::<io::Result<Vec<MountInfo>>>()?))
+    }
+
+    /// Read a new list of mounts into memory from the mount namespace referred to by a
+    /// namespace file descriptor.
+    pub fn new_from_namespace<F: AsRawFd>(namespace: &F) -> io::Result<MountList> {
+        Ok(MountList(
+            MountIter::new_from_namespace(namespace)?.collect::<io::Result<Vec<MountInfo>>>()?,
+        ))
+    }
+
     /// Read a new list of mounts into memory from any mount-tab-like file.
     pub fn new_from_reader<R: BufRead>(reader: R) -> io::Result<MountList> {
         Ok(MountList(MountIter::new_from_reader(reader).collect::<io::Result<Vec<MountInfo>>>()?))
@@ -42,37 +62,64 @@ impl MountList {
         self.0.iter().find(|mount| mount.dest == path.as_ref())
     }
 
+    /// Find the mount which contains the given `path`.
+    ///
+    /// The mount with the longest destination that is a parent of `path` is chosen, comparing
+    /// whole path components. If several mounts share that destination, the last one is
+    /// chosen, as it is mounted over the others. The `path` is not resolved, so it should be
+    /// absolute and free of symlinks.
+    pub fn get_mount_containing<P: AsRef<Path>>(&self, path: P) -> Option<&MountInfo> {
+        let path = path.as_ref();
+        self.0
+            .iter()
+            .filter(|mount| path.starts_with(&mount.dest))
+            .max_by_key(|mount| mount.dest.components().count())
+    }
+
+    /// Find the mount which contains the file at the given `path`, after resolving symlinks in
+    /// the path.
+    ///
+    /// Unlike `MountInfoList::get_mount_for_file`, the mount is matched by its path only, as
+    /// `/proc/mounts` does not record device numbers. The answer may therefore be wrong if the
+    /// list is out of date with the visible mount table.
+    pub fn get_mount_for_file<P: AsRef<Path>>(&self, path: P) -> io::Result<Option<&MountInfo>> {
+        Ok(self.get_mount_containing(fs::canonicalize(path)?))
+    }
+
     /// Find the first mount hich has the source `path`.
     pub fn get_mount_by_source<P: AsRef<Path>>(&self, path: P) -> Option<&MountInfo> {
         self.0.iter().find(|mount| mount.source == path.as_ref())
     }
 
     /// Iterate through each source that starts with the given `path`.
+    ///
+    /// Sources are compared as they were specified, so `UUID=`, network, and pseudo sources
+    /// such as `server:` or `sys` may also be matched.
     pub fn source_starts_with<'a>(
         &'a self,
         path: &'a Path,
     ) -> Box<dyn Iterator<Item = &MountInfo> + 'a> {
-        self.starts_with(path.as_os_str().as_bytes(), |m| &m.source)
+        let path = path.as_os_str().as_bytes();
+        let iterator = self.0.iter().filter(move |mount| {
+            let (tag, value) = mount.source.parts();
+            let (tag, value) = (tag.as_bytes(), value.as_bytes());
+            match path.get(..tag.len()) {
+                Some(prefix) => prefix == tag && value.starts_with(&path[tag.len()..]),
+                None => tag.starts_with(path),
+            }
+        });
+
+        Box::new(iterator)
     }
 
-    /// Iterate through each destination that starts with the given `path`.
+    /// Iterate through each destination that is at or beneath the given `path`.
+    ///
+    /// Unlike `source_starts_with`, paths are compared by whole components, so `/mnt/data`
+    /// will not match `/mnt/data2`.
     pub fn destination_starts_with<'a>(
         &'a self,
         path: &'a Path,
     ) -> Box<dyn Iterator<Item = &MountInfo> + 'a> {
-        self.starts_with(path.as_os_str().as_bytes(), |m| &m.dest)
-    }
-
-    fn starts_with<'a, F: Fn(&'a MountInfo) -> &'a Path + 'a>(
-        &'a self,
-        path: &'a [u8],
-        func: F,
-    ) -> Box<dyn Iterator<Item = &MountInfo> + 'a> {
-        let iterator = self.0.iter().filter(move |mount| {
-            let input = func(mount).as_os_str().as_bytes();
-            input.len() >= path.len() && &input[..path.len()] == path
-        });
-
-        Box::new(iterator)
+        Box::new(self.0.iter().filter(move |mount| mount.dest.starts_with(path)))
     }
 }
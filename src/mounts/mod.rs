@@ -1,9 +1,16 @@
+mod diff;
 mod info;
 mod iter;
+mod kernel;
 mod list;
+mod options;
+mod source;
 mod tab;
+mod validate;
 
-pub use self::{info::*, iter::*, list::*, tab::*};
+pub use self::{
+    diff::*, info::*, iter::*, kernel::*, list::*, options::*, source::*, tab::*, validate::*,
+};
 
 #[cfg(test)]
 mod tests {
@@ -17,7 +24,8 @@ tmpfs /run tmpfs rw,nosuid,noexec,relatime,size=3291052k,mode=755 0 0
 /dev/sda2 / ext4 rw,noatime,errors=remount-ro,data=ordered
 fusectl /sys/fs/fuse/connections fusectl rw,relatime 0 0
 /dev/sda1 /boot/efi vfat rw,relatime,fmask=0077,dmask=0077,codepage=437,iocharset=iso8859-1,shortname=mixed,errors=remount-ro 0 0
-/dev/sda6 /mnt/data ext4 rw,noatime,data=ordered 0 0"#;
+/dev/sda6 /mnt/data ext4 rw,noatime,data=ordered 0 0
+/dev/sda7 /mnt/data2 ext4 rw,noatime,data=ordered 0 0"#;
 
     #[test]
     fn source_mounted_at() {
@@ -33,19 +41,12 @@ fusectl /sys/fs/fuse/connections fusectl rw,relatime 0 0
         assert_eq!(
             mounts.get_mount_by_source(Path::new("/dev/sda1")).unwrap(),
             &MountInfo {
-                source:  PathBuf::from("/dev/sda1"),
+                source:  MountSource::Path(PathBuf::from("/dev/sda1")),
                 dest:    PathBuf::from("/boot/efi"),
                 fstype:  "vfat".into(),
-                options: vec![
-                    "rw".into(),
-                    "relatime".into(),
-                    "fmask=0077".into(),
-                    "dmask=0077".into(),
-                    "codepage=437".into(),
-                    "iocharset=iso8859-1".into(),
-                    "shortname=mixed".into(),
-                    "errors=remount-ro".into(),
-                ],
+                options: "rw,relatime,fmask=0077,dmask=0077,codepage=437,iocharset=iso8859-1,\
+                          shortname=mixed,errors=remount-ro"
+                    .into(),
                 dump:    0,
                 pass:    0,
             }
@@ -62,8 +63,156 @@ fusectl /sys/fs/fuse/connections fusectl rw,relatime 0 0
                 PathBuf::from("/"),
                 PathBuf::from("/sys/fs/fuse/connections"),
                 PathBuf::from("/boot/efi"),
-                PathBuf::from("/mnt/data")
+                PathBuf::from("/mnt/data"),
+                PathBuf::from("/mnt/data2")
             ]
         );
+
+        let path = &Path::new("/mnt/data");
+        assert_eq!(
+            mounts.destination_starts_with(path).map(|m| m.dest.clone()).collect::<Vec<PathBuf>>(),
+            vec![PathBuf::from("/mnt/data")]
+        );
+
+        let sources = |mounts: &MountList, prefix: &str| {
+            mounts
+                .source_starts_with(Path::new(prefix))
+                .map(|m| m.source.to_string())
+                .collect::<Vec<String>>()
+        };
+
+        assert_eq!(
+            sources(&mounts, "/dev/sda"),
+            vec!["/dev/sda2", "/dev/sda1", "/dev/sda6", "/dev/sda7"]
+        );
+        assert_eq!(sources(&mounts, "sys"), vec!["sysfs"]);
+
+        let mounts = MountList::parse_from(
+            "server:/export /mnt/nfs nfs4 rw 0 0\nUUID=0a1b /home ext4 rw 0 0".lines(),
+        )
+        .unwrap();
+        assert_eq!(sources(&mounts, "server:"), vec!["server:/export"]);
+        assert_eq!(sources(&mounts, "UU"), vec!["UUID=0a1b"]);
+        assert_eq!(sources(&mounts, "UUID=0a"), vec!["UUID=0a1b"]);
+        assert!(sources(&mounts, "UUID=1").is_empty());
+    }
+
+    #[test]
+    fn mount_containing() {
+        let mounts = MountList::parse_from(SAMPLE.lines()).unwrap();
+        let dest = |path| mounts.get_mount_containing(path).map(|m| m.dest.clone());
+
+        assert_eq!(dest("/mnt/data/file"), Some(PathBuf::from("/mnt/data")));
+        assert_eq!(dest("/mnt/data2"), Some(PathBuf::from("/mnt/data2")));
+        assert_eq!(dest("/mnt/data3"), Some(PathBuf::from("/")));
+        assert_eq!(dest("/boot/efi/EFI"), Some(PathBuf::from("/boot/efi")));
+        assert_eq!(dest("relative"), None);
+
+        let root = mounts.get_mount_for_file("/").unwrap().unwrap();
+        assert_eq!(root.dest, PathBuf::from("/"));
+    }
+
+    #[test]
+    fn sources() {
+        let source = |spec: &str| spec.parse::<MountSource>().unwrap();
+
+        assert_eq!(source("/dev/sda1"), MountSource::Path(PathBuf::from("/dev/sda1")));
+        assert_eq!(source("UUID=1234-ABCD"), MountSource::Uuid("1234-ABCD".into()));
+        assert_eq!(source("PARTUUID=00c9-01"), MountSource::PartUuid("00c9-01".into()));
+        assert_eq!(source("LABEL=Data"), MountSource::Label("Data".into()));
+        assert_eq!(source("PARTLABEL=EFI"), MountSource::PartLabel("EFI".into()));
+        assert_eq!(source("server:/export"), MountSource::Network("server:/export".into()));
+        assert_eq!(source("//server/share"), MountSource::Network("//server/share".into()));
+        assert_eq!(source("tmpfs"), MountSource::Pseudo("tmpfs".into()));
+        assert_eq!(source("UUID="), MountSource::Pseudo("UUID=".into()));
+
+        for spec in &["/dev/sda1", "UUID=1234-ABCD", "LABEL=Data", "server:/export", "proc"] {
+            assert_eq!(source(spec).to_string(), *spec);
+        }
+
+        assert!(source("proc").resolve().is_err());
+        assert_eq!(source("/dev/sda1").resolve().unwrap(), PathBuf::from("/dev/sda1"));
+
+        let mount = "UUID=1234-ABCD /boot/efi vfat umask=0077 0 1".parse::<MountInfo>().unwrap();
+        assert_eq!(mount.source, MountSource::Uuid("1234-ABCD".into()));
+        assert_eq!(mount.to_string(), "UUID=1234-ABCD /boot/efi vfat umask=0077 0 1");
+
+        let mounts = MountList::parse_from(SAMPLE.lines()).unwrap();
+        assert_eq!(mounts.get_mount_by_source("sysfs").unwrap().dest, PathBuf::from("/sys"));
+    }
+
+    #[test]
+    fn escaped_round_trip() {
+        let mount = MountInfo {
+            source:  MountSource::Label("My Disk".into()),
+            dest:    PathBuf::from("/media/my disk\\with\ttabs\nand newlines"),
+            fstype:  "ext4".into(),
+            options: "defaults".into(),
+            dump:    0,
+            pass:    2,
+        };
+
+        let line = mount.to_string();
+        assert_eq!(
+            line,
+            "LABEL=My\\040Disk /media/my\\040disk\\134with\\011tabs\\012and\\040newlines ext4 \
+             defaults 0 2"
+        );
+        assert_eq!(line.parse::<MountInfo>().unwrap(), mount);
+
+        let tab = MountTab(vec![mount.into()]);
+        assert_eq!(tab.to_string().parse::<MountTab>().unwrap(), tab);
+    }
+
+    #[test]
+    fn non_utf8() {
+        use std::{ffi::OsStr, io, os::unix::ffi::OsStrExt};
+
+        let line = b"LABEL=USB\\040\\377 /media/usb\\040\\377\\376 vfat rw,nosuid 0 0";
+        let mount = MountInfo::from_bytes(line).unwrap();
+        assert_eq!(mount.source, MountSource::Label(OsStr::from_bytes(b"USB \xff").into()));
+        assert_eq!(mount.dest, Path::new(OsStr::from_bytes(b"/media/usb \xff\xfe")));
+
+        let mut input = b"/dev/sda1 / ext4 rw 0 0\n".to_vec();
+        input.extend_from_slice(b"/dev/sdb1 /media/\xff vfat rw 0 0\n");
+        let mounts = MountIter::new_from_reader(&input[..]).collect::<io::Result<Vec<_>>>();
+        assert_eq!(mounts.unwrap()[1].dest, Path::new(OsStr::from_bytes(b"/media/\xff")));
+
+        let line = mount.to_string();
+        assert_eq!(line, "LABEL=USB\\040\\377 /media/usb\\040\\377\\376 vfat rw,nosuid 0 0");
+        assert_eq!(line.parse::<MountInfo>().unwrap(), mount);
+
+        let mut bytes = Vec::new();
+        mount.write_bytes(&mut bytes).unwrap();
+        assert_eq!(bytes, b"LABEL=USB\\040\xff /media/usb\\040\xff\xfe vfat rw,nosuid 0 0");
+        assert_eq!(MountInfo::from_bytes(&bytes).unwrap(), mount);
+    }
+
+    #[test]
+    fn diff() {
+        let old = MountList::parse_from(SAMPLE.lines()).unwrap();
+        let mut new = old.clone();
+        new.0[4].options = "ro,noatime,errors=remount-ro,data=ordered".into();
+        new.0[7].dest = PathBuf::from("/srv/data");
+        new.0[3].dest = PathBuf::from("/tmp");
+        new.0.remove(8);
+        new.0.push("/dev/sdb1 /media/usb vfat rw 0 0".parse().unwrap());
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.added, vec![new.0[3].clone(), new.0[8].clone()]);
+        assert_eq!(diff.removed, vec![old.0[3].clone(), old.0[8].clone()]);
+        assert_eq!(diff.remounted, vec![Change { old: old.0[4].clone(), new: new.0[4].clone() }]);
+        assert_eq!(diff.moved, vec![Change { old: old.0[7].clone(), new: new.0[7].clone() }]);
+
+        assert!(old.diff(&old).is_empty());
+        assert!(new.diff(&old).added.contains(&old.0[8]));
+
+        // Identical mounts stacked on the same destination are counted, not merely looked up.
+        let stacked: MountInfo = "tmpfs /tmp tmpfs rw 0 0".parse().unwrap();
+        let single = MountList(vec![stacked.clone()]);
+        let double = MountList(vec![stacked.clone(), stacked.clone()]);
+        assert_eq!(double.diff(&single).removed, vec![stacked.clone()]);
+        assert_eq!(single.diff(&double).added, vec![stacked]);
+        assert!(double.diff(&double).is_empty());
     }
 }
@@ -0,0 +1,428 @@
+use std::{
+    convert::Infallible,
+    fmt::{self, Display, Formatter},
+    io::{self, Error, ErrorKind},
+    iter::FromIterator,
+    mem, slice,
+    str::FromStr,
+    vec,
+};
+
+/// A mount option which is a boolean flag, such as `ro` or `nosuid`.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub enum MountFlag {
+    /// `ro`: the file system is mounted read-only.
+    ReadOnly,
+    /// `rw`: the file system is mounted read-write.
+    ReadWrite,
+    /// `suid`: set-user-ID and set-group-ID bits take effect.
+    Suid,
+    /// `nosuid`: set-user-ID and set-group-ID bits are ignored.
+    NoSuid,
+    /// `dev`: device files may be accessed.
+    Dev,
+    /// `nodev`: device files may not be accessed.
+    NoDev,
+    /// `exec`: programs may be executed.
+    Exec,
+    /// `noexec`: programs may not be executed.
+    NoExec,
+    /// `sync`: all I/O is done synchronously.
+    Sync,
+    /// `async`: all I/O is done asynchronously.
+    Async,
+    /// `dirsync`: directory updates are done synchronously.
+    DirSync,
+    /// `mand`: mandatory locks are permitted.
+    Mand,
+    /// `nomand`: mandatory locks are not permitted.
+    NoMand,
+    /// `atime`: access times are updated according to the kernel's default.
+    Atime,
+    /// `noatime`: access times are never updated.
+    NoAtime,
+    /// `relatime`: access times are updated relative to the modify or change time.
+    RelAtime,
+    /// `norelatime`: access times are not updated relative to the modify or change time.
+    NoRelAtime,
+    /// `strictatime`: access times are updated on every access.
+    StrictAtime,
+    /// `nostrictatime`: access times are updated according to the kernel's default.
+    NoStrictAtime,
+    /// `diratime`: access times of directories are updated.
+    DirAtime,
+    /// `nodiratime`: access times of directories are never updated.
+    NoDirAtime,
+    /// `lazytime`: timestamps are only kept in memory until they need to be written.
+    LazyTime,
+    /// `nolazytime`: timestamps are written as they change.
+    NoLazyTime,
+    /// `silent`: some kernel messages are suppressed.
+    Silent,
+    /// `loud`: kernel messages are not suppressed.
+    Loud,
+}
+
+pub(super) const FLAGS: &[(MountFlag, &str)] = &[
+    (MountFlag::ReadOnly, "ro"),
+    (MountFlag::ReadWrite, "rw"),
+    (MountFlag::Suid, "suid"),
+    (MountFlag::NoSuid, "nosuid"),
+    (MountFlag::Dev, "dev"),
+    (MountFlag::NoDev, "nodev"),
+    (MountFlag::Exec, "exec"),
+    (MountFlag::NoExec, "noexec"),
+    (MountFlag::Sync, "sync"),
+    (MountFlag::Async, "async"),
+    (MountFlag::DirSync, "dirsync"),
+    (MountFlag::Mand, "mand"),
+    (MountFlag::NoMand, "nomand"),
+    (MountFlag::Atime, "atime"),
+    (MountFlag::NoAtime, "noatime"),
+    (MountFlag::RelAtime, "relatime"),
+    (MountFlag::NoRelAtime, "norelatime"),
+    (MountFlag::StrictAtime, "strictatime"),
+    (MountFlag::NoStrictAtime, "nostrictatime"),
+    (MountFlag::DirAtime, "diratime"),
+    (MountFlag::NoDirAtime, "nodiratime"),
+    (MountFlag::LazyTime, "lazytime"),
+    (MountFlag::NoLazyTime, "nolazytime"),
+    (MountFlag::Silent, "silent"),
+    (MountFlag::Loud, "loud"),
+];
+
+impl MountFlag {
+    /// The name of the option which sets this flag.
+    pub fn as_str(self) -> &'static str {
+        use MountFlag::*;
+        match self {
+            ReadOnly => "ro",
+            ReadWrite => "rw",
+            Suid => "suid",
+            NoSuid => "nosuid",
+            Dev => "dev",
+            NoDev => "nodev",
+            Exec => "exec",
+            NoExec => "noexec",
+            Sync => "sync",
+            Async => "async",
+            DirSync => "dirsync",
+            Mand => "mand",
+            NoMand => "nomand",
+            Atime => "atime",
+            NoAtime => "noatime",
+            RelAtime => "relatime",
+            NoRelAtime => "norelatime",
+            StrictAtime => "strictatime",
+            NoStrictAtime => "nostrictatime",
+            DirAtime => "diratime",
+            NoDirAtime => "nodiratime",
+            LazyTime => "lazytime",
+            NoLazyTime => "nolazytime",
+            Silent => "silent",
+            Loud => "loud",
+        }
+    }
+
+    /// The flag which undoes this flag, such as `rw` for `ro`.
+    pub fn opposite(self) -> Option<MountFlag> {
+        use MountFlag::*;
+        let opposite = match self {
+            ReadOnly => ReadWrite,
+            ReadWrite => ReadOnly,
+            Suid => NoSuid,
+            NoSuid => Suid,
+            Dev => NoDev,
+            NoDev => Dev,
+            Exec => NoExec,
+            NoExec => Exec,
+            Sync => Async,
+            Async => Sync,
+            Mand => NoMand,
+            NoMand => Mand,
+            DirAtime => NoDirAtime,
+            NoDirAtime => DirAtime,
+            LazyTime => NoLazyTime,
+            NoLazyTime => LazyTime,
+            Silent => Loud,
+            Loud => Silent,
+            DirSync | Atime | NoAtime | RelAtime | NoRelAtime | StrictAtime | NoStrictAtime => {
+                return None
+            }
+        };
+
+        Some(opposite)
+    }
+
+    /// Returns true if setting this flag overrides the `other` flag.
+    ///
+    /// Besides opposing flags, each of the flags which select how access times are updated
+    /// override one another.
+    pub fn conflicts_with(self, other: MountFlag) -> bool {
+        use MountFlag::*;
+        let is_atime = |flag| {
+            matches!(flag, Atime | NoAtime | RelAtime | NoRelAtime | StrictAtime | NoStrictAtime)
+        };
+
+        self != other && (self.opposite() == Some(other) || (is_atime(self) && is_atime(other)))
+    }
+}
+
+impl Display for MountFlag {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result { fmt.write_str(self.as_str()) }
+}
+
+impl FromStr for MountFlag {
+    type Err = io::Error;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        FLAGS.iter().find(|(_, flag)| *flag == name).map(|(flag, _)| *flag).ok_or_else(|| {
+            Error::new(ErrorKind::InvalidInput, format!("{} is not a mount flag", name))
+        })
+    }
+}
+
+/// A single mount option, which is either a bare `key` or a `key=value` pair.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Default, Clone, Hash, Eq, PartialEq)]
+pub struct MountOption {
+    /// The name of the option.
+    pub key:   String,
+    /// The value which follows the first `=`, if there is one.
+    pub value: Option<String>,
+}
+
+impl MountOption {
+    /// The flag which this option sets, if it is a flag.
+    pub fn flag(&self) -> Option<MountFlag> {
+        if self.value.is_some() {
+            return None;
+        }
+
+        self.key.parse::<MountFlag>().ok()
+    }
+}
+
+impl Display for MountOption {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        match self.value {
+            Some(ref value) => write!(fmt, "{}={}", self.key, value),
+            None => fmt.write_str(&self.key),
+        }
+    }
+}
+
+impl<'a> From<&'a str> for MountOption {
+    fn from(option: &'a str) -> Self {
+        match option.split_once('=') {
+            Some((key, value)) => MountOption { key: key.into(), value: Some(value.into()) },
+            None => MountOption { key: option.into(), value: None },
+        }
+    }
+}
+
+impl From<MountFlag> for MountOption {
+    fn from(flag: MountFlag) -> Self { MountOption { key: flag.as_str().into(), value: None } }
+}
+
+/// The comma-separated options of a mount, in the order that they were specified.
+///
+/// Options which appear later take precedence over earlier ones, as they do when mounting.
+#[derive(Debug, Default, Clone, Hash, Eq, PartialEq)]
+pub struct MountOptions(Vec<MountOption>);
+
+impl MountOptions {
+    /// An empty list of options, which is displayed as `defaults`.
+    pub fn new() -> Self { Self::default() }
+
+    /// Returns true if the last of the `ro` and `rw` flags is `ro`.
+    pub fn is_read_only(&self) -> bool {
+        self.0
+            .iter()
+            .rev()
+            .find_map(|option| match option.flag() {
+                Some(MountFlag::ReadOnly) => Some(true),
+                Some(MountFlag::ReadWrite) => Some(false),
+                _ => None,
+            })
+            .unwrap_or(false)
+    }
+
+    /// Returns true if the given flag is set, and has not been overridden by a later flag.
+    pub fn is_set(&self, flag: MountFlag) -> bool {
+        self.0
+            .iter()
+            .rev()
+            .filter_map(MountOption::flag)
+            .find(|&other| other == flag || flag.conflicts_with(other))
+            == Some(flag)
+    }
+
+    /// Set a flag, removing every flag which it would override.
+    pub fn set(&mut self, flag: MountFlag) {
+        self.0.retain(|option| option.flag().filter(|&other| flag.conflicts_with(other)).is_none());
+        if !self.0.iter().any(|option| option.flag() == Some(flag)) {
+            self.0.push(flag.into());
+        }
+    }
+
+    /// Remove a flag, returning true if it was present.
+    pub fn unset(&mut self, flag: MountFlag) -> bool {
+        let len = self.0.len();
+        self.0.retain(|option| option.flag() != Some(flag));
+        len != self.0.len()
+    }
+
+    /// Iterate through the flags which are set, in the order that they were specified.
+    pub fn flags(&self) -> impl Iterator<Item = MountFlag> + '_ {
+        self.0.iter().filter_map(MountOption::flag)
+    }
+
+    /// The value of the last `key=value` option with the given key.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.iter().rev().find(|option| option.key == key)?.value.as_deref()
+    }
+
+    /// Returns true if an option with the given key is present, with or without a value.
+    pub fn contains(&self, key: &str) -> bool { self.0.iter().any(|option| option.key == key) }
+
+    /// Set the value of an option, returning its previous value.
+    ///
+    /// An existing option with the same key is updated where it is; otherwise the option is
+    /// appended. Any further options with the same key are removed.
+    pub fn insert<K: Into<String>, V: Into<String>>(&mut self, key: K, value: V) -> Option<String> {
+        let key = key.into();
+        let value = Some(value.into());
+
+        match self.0.iter().position(|option| option.key == key) {
+            Some(index) => {
+                let previous = mem::replace(&mut self.0[index].value, value);
+                let rest = self.0.split_off(index + 1);
+                self.0.extend(rest.into_iter().filter(|option| option.key != key));
+                previous
+            }
+            None => {
+                self.0.push(MountOption { key, value });
+                None
+            }
+        }
+    }
+
+    /// Remove every option with the given key, returning the first one that was removed.
+    pub fn remove(&mut self, key: &str) -> Option<MountOption> {
+        let index = self.0.iter().position(|option| option.key == key)?;
+        let removed = self.0.remove(index);
+        self.0.retain(|option| option.key != key);
+        Some(removed)
+    }
+
+    /// Append an option, without removing any existing options.
+    pub fn push<O: Into<MountOption>>(&mut self, option: O) { self.0.push(option.into()); }
+
+    /// Iterate through each option, in the order that they were specified.
+    pub fn iter(&self) -> slice::Iter<'_, MountOption> { self.0.iter() }
+
+    /// The number of options.
+    pub fn len(&self) -> usize { self.0.len() }
+
+    /// Returns true if there are no options, which is written as `defaults`.
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+}
+
+impl Display for MountOptions {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        if self.0.is_empty() {
+            return fmt.write_str("defaults");
+        }
+
+        for (index, option) in self.0.iter().enumerate() {
+            if index != 0 {
+                fmt.write_str(",")?;
+            }
+
+            write!(fmt, "{}", option)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> From<&'a str> for MountOptions {
+    /// Split a comma-separated list of options, ignoring empty options.
+    fn from(options: &'a str) -> Self {
+        options.split(',').filter(|option| !option.is_empty()).map(MountOption::from).collect()
+    }
+}
+
+impl FromStr for MountOptions {
+    type Err = Infallible;
+
+    fn from_str(options: &str) -> Result<Self, Self::Err> { Ok(Self::from(options)) }
+}
+
+impl<O: Into<MountOption>> FromIterator<O> for MountOptions {
+    fn from_iter<I: IntoIterator<Item = O>>(iter: I) -> Self {
+        MountOptions(iter.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<O: Into<MountOption>> Extend<O> for MountOptions {
+    fn extend<I: IntoIterator<Item = O>>(&mut self, iter: I) {
+        self.0.extend(iter.into_iter().map(Into::into));
+    }
+}
+
+impl IntoIterator for MountOptions {
+    type IntoIter = vec::IntoIter<MountOption>;
+    type Item = MountOption;
+
+    fn into_iter(self) -> Self::IntoIter { self.0.into_iter() }
+}
+
+impl<'a> IntoIterator for &'a MountOptions {
+    type IntoIter = slice::Iter<'a, MountOption>;
+    type Item = &'a MountOption;
+
+    fn into_iter(self) -> Self::IntoIter { self.0.iter() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn options() {
+        let mut options = MountOptions::from("rw,nosuid,size=3291048k,mode=700,relatime");
+        assert!(!options.is_read_only());
+        assert!(options.is_set(MountFlag::NoSuid));
+        assert!(options.is_set(MountFlag::RelAtime));
+        assert_eq!(options.get("size"), Some("3291048k"));
+        assert_eq!(options.get("nosuid"), None);
+        assert!(options.contains("nosuid"));
+
+        options.set(MountFlag::ReadOnly);
+        options.set(MountFlag::NoAtime);
+        assert!(options.is_read_only());
+        assert!(!options.is_set(MountFlag::RelAtime));
+        assert_eq!(options.to_string(), "nosuid,size=3291048k,mode=700,ro,noatime");
+
+        assert_eq!(options.insert("mode", "755"), Some("700".into()));
+        assert_eq!(options.insert("uid", "1000"), None);
+        assert_eq!(options.remove("size").and_then(|option| option.value), Some("3291048k".into()));
+        assert!(options.unset(MountFlag::NoSuid));
+        assert_eq!(options.to_string(), "mode=755,ro,noatime,uid=1000");
+
+        let mut options = MountOptions::from("ro,rw");
+        assert!(!options.is_read_only());
+        assert!(!options.is_set(MountFlag::ReadOnly));
+        options.remove("ro");
+        options.remove("rw");
+        assert_eq!(options.to_string(), "defaults");
+        assert!(options.is_empty());
+
+        for &(flag, name) in FLAGS {
+            assert_eq!(flag.as_str(), name);
+            assert_eq!(name.parse::<MountFlag>().unwrap(), flag);
+        }
+    }
+}
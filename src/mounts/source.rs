@@ -0,0 +1,191 @@
+use crate::escape::{self, Escaped};
+use partition_identity::{PartitionID, PartitionSource};
+use std::{
+    convert::Infallible,
+    ffi::{OsStr, OsString},
+    fmt::{self, Display, Formatter},
+    io::{self, Error, ErrorKind},
+    os::unix::ffi::{OsStrExt, OsStringExt},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// The source of a mount, as it was specified in a mount tab or the kernel's mount table.
+///
+/// The original form of the source is kept, so that it is written back as it was read. Use
+/// `resolve` to find the device that the source refers to.
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub enum MountSource {
+    /// A path to a device or file, such as `/dev/sda2` or `/dev/disk/by-uuid/...`.
+    Path(PathBuf),
+    /// A file system UUID, specified as `UUID=`.
+    Uuid(String),
+    /// A partition UUID, specified as `PARTUUID=`.
+    PartUuid(String),
+    /// A file system label, specified as `LABEL=`.
+    Label(OsString),
+    /// A partition label, specified as `PARTLABEL=`.
+    PartLabel(OsString),
+    /// A network share, such as `server:/export` or `//server/share`.
+    Network(OsString),
+    /// A source which does not refer to a device, such as `proc`, `tmpfs`, or `none`.
+    Pseudo(OsString),
+}
+
+impl MountSource {
+    /// The path of the source, if it is specified as a path.
+    pub fn as_path(&self) -> Option<&Path> {
+        match self {
+            MountSource::Path(ref path) => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the source refers to a block device or file, rather than a network
+    /// share or a pseudo file system.
+    pub fn is_device(&self) -> bool {
+        !matches!(self, MountSource::Network(_) | MountSource::Pseudo(_))
+    }
+
+    /// Resolve the source to the path of the device that it refers to.
+    ///
+    /// `UUID=`, `PARTUUID=`, `LABEL=`, and `PARTLABEL=` sources are looked up in
+    /// `/dev/disk/by-*`, and `/dev/disk/by-*` paths are resolved to the device that they link
+    /// to. Other paths are returned as they are.
+    pub fn resolve(&self) -> io::Result<PathBuf> {
+        let (variant, id) = match self {
+            MountSource::Path(ref path) if path.starts_with("/dev/disk/by-") => {
+                return fetch_from_disk_by_path(path);
+            }
+            MountSource::Path(ref path) => return Ok(path.clone()),
+            MountSource::Uuid(ref id) => (PartitionSource::UUID, id.as_str()),
+            MountSource::PartUuid(ref id) => (PartitionSource::PartUUID, id.as_str()),
+            MountSource::Label(ref id) => (PartitionSource::Label, utf8(id)?),
+            MountSource::PartLabel(ref id) => (PartitionSource::PartLabel, utf8(id)?),
+            MountSource::Network(_) | MountSource::Pseudo(_) => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("{} does not refer to a device", self),
+                ));
+            }
+        };
+
+        PartitionID::new(variant, id.to_owned()).get_device_path().ok_or_else(|| {
+            Error::new(ErrorKind::NotFound, format!("device path for {} was not found", self))
+        })
+    }
+
+    /// Encode the source in its original form, with `\NNN` octal escapes for whitespace and
+    /// backslashes.
+    pub(crate) fn encode(&self, out: &mut Vec<u8>) {
+        let (tag, value) = self.parts();
+        out.extend_from_slice(tag.as_bytes());
+        escape::encode(value, out);
+    }
+
+    /// The tag which the source was specified with, such as `UUID=`, and the value which
+    /// follows it.
+    pub(crate) fn parts(&self) -> (&'static str, &OsStr) {
+        match self {
+            MountSource::Path(ref path) => ("", path.as_os_str()),
+            MountSource::Uuid(ref id) => ("UUID=", id.as_ref()),
+            MountSource::PartUuid(ref id) => ("PARTUUID=", id.as_ref()),
+            MountSource::Label(ref id) => ("LABEL=", id),
+            MountSource::PartLabel(ref id) => ("PARTLABEL=", id),
+            MountSource::Network(ref spec) | MountSource::Pseudo(ref spec) => ("", spec),
+        }
+    }
+}
+
+fn utf8(value: &OsStr) -> io::Result<&str> {
+    value.to_str().ok_or_else(|| Error::new(ErrorKind::InvalidData, "non-utf8 label"))
+}
+
+fn fetch_from_disk_by_path(path: &Path) -> io::Result<PathBuf> {
+    let path = utf8(path.as_os_str())?;
+    PartitionID::from_disk_by_path(path)
+        .map_err(|why| Error::new(ErrorKind::InvalidData, format!("{}: {}", path, why)))?
+        .get_device_path()
+        .ok_or_else(|| {
+            Error::new(ErrorKind::NotFound, format!("device path for {} was not found", path))
+        })
+}
+
+impl Default for MountSource {
+    fn default() -> Self { MountSource::Path(PathBuf::new()) }
+}
+
+impl Display for MountSource {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        match self {
+            MountSource::Path(ref path) => write!(fmt, "{}", Escaped(path.as_os_str())),
+            MountSource::Uuid(ref id) => write!(fmt, "UUID={}", Escaped(id.as_ref())),
+            MountSource::PartUuid(ref id) => write!(fmt, "PARTUUID={}", Escaped(id.as_ref())),
+            MountSource::Label(ref id) => write!(fmt, "LABEL={}", Escaped(id)),
+            MountSource::PartLabel(ref id) => write!(fmt, "PARTLABEL={}", Escaped(id)),
+            MountSource::Network(ref spec) | MountSource::Pseudo(ref spec) => {
+                write!(fmt, "{}", Escaped(spec))
+            }
+        }
+    }
+}
+
+impl From<OsString> for MountSource {
+    fn from(source: OsString) -> Self {
+        let bytes = source.as_bytes();
+
+        let tagged = |tag: &[u8]| -> Option<OsString> {
+            if bytes.len() > tag.len() && bytes.starts_with(tag) {
+                Some(OsString::from_vec(bytes[tag.len()..].to_vec()))
+            } else {
+                None
+            }
+        };
+
+        let tagged_utf8 = |tag: &[u8]| tagged(tag).and_then(|id| id.into_string().ok());
+
+        if bytes.starts_with(b"/") && !bytes.starts_with(b"//") {
+            MountSource::Path(PathBuf::from(source))
+        } else if let Some(id) = tagged_utf8(b"UUID=") {
+            MountSource::Uuid(id)
+        } else if let Some(id) = tagged_utf8(b"PARTUUID=") {
+            MountSource::PartUuid(id)
+        } else if let Some(id) = tagged(b"LABEL=") {
+            MountSource::Label(id)
+        } else if let Some(id) = tagged(b"PARTLABEL=") {
+            MountSource::PartLabel(id)
+        } else if bytes.starts_with(b"//") || bytes.contains(&b':') {
+            MountSource::Network(source)
+        } else {
+            MountSource::Pseudo(source)
+        }
+    }
+}
+
+impl From<PathBuf> for MountSource {
+    fn from(source: PathBuf) -> Self { Self::from(source.into_os_string()) }
+}
+
+impl<'a> From<&'a str> for MountSource {
+    fn from(source: &'a str) -> Self { Self::from(OsString::from(source)) }
+}
+
+impl FromStr for MountSource {
+    type Err = Infallible;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> { Ok(Self::from(source)) }
+}
+
+impl PartialEq<Path> for MountSource {
+    /// Compares the source with a path, which is classified in the same way as a parsed source,
+    /// so that `UUID=` and pseudo sources can be compared too.
+    fn eq(&self, other: &Path) -> bool { *self == Self::from(other.as_os_str().to_owned()) }
+}
+
+impl<'a> PartialEq<&'a Path> for MountSource {
+    fn eq(&self, other: &&'a Path) -> bool { *self == **other }
+}
+
+impl PartialEq<PathBuf> for MountSource {
+    fn eq(&self, other: &PathBuf) -> bool { *self == *other.as_path() }
+}
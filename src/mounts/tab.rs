@@ -1,4 +1,5 @@
 use super::MountInfo;
+use crate::error;
 use std::{
     fmt::{self, Display, Formatter},
     io,
@@ -7,7 +8,9 @@ use std::{
 };
 
 /// An element in an abtract representation of the mount tab that was read into memory.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
 pub enum AbstractMountElement {
     /// An element which is a comment
     Comment(String),
@@ -15,6 +18,31 @@ pub enum AbstractMountElement {
     Empty,
     /// An element which defines a mount point
     Mount(MountInfo),
+    /// An element which defines a mount point, and which retains the line it was parsed from
+    Preserved(PreservedMount),
+    /// A line which is written exactly as it was read, including its line terminator, such as
+    /// a comment or blank line from `MountTab::from_str_lossless`.
+    Verbatim(String),
+}
+
+impl AbstractMountElement {
+    /// The mount entry of the element, if it defines a mount point.
+    pub fn as_mount(&self) -> Option<&MountInfo> {
+        match self {
+            AbstractMountElement::Mount(ref entry) => Some(entry),
+            AbstractMountElement::Preserved(ref entry) => Some(&entry.info),
+            _ => None,
+        }
+    }
+
+    /// The line terminator which follows the element when the tab is displayed.
+    fn terminator(&self) -> &str {
+        match self {
+            AbstractMountElement::Preserved(ref entry) => &entry.terminator,
+            AbstractMountElement::Verbatim(_) => "",
+            _ => "\n",
+        }
+    }
 }
 
 impl Display for AbstractMountElement {
@@ -23,6 +51,8 @@ impl Display for AbstractMountElement {
             AbstractMountElement::Comment(ref comment) => fmt.write_str(comment),
             AbstractMountElement::Empty => Ok(()),
             AbstractMountElement::Mount(ref entry) => fmt.write_fmt(format_args!("{}", entry)),
+            AbstractMountElement::Preserved(ref entry) => fmt.write_fmt(format_args!("{}", entry)),
+            AbstractMountElement::Verbatim(ref line) => fmt.write_str(line),
         }
     }
 }
@@ -39,32 +69,138 @@ impl From<MountInfo> for AbstractMountElement {
     fn from(info: MountInfo) -> Self { AbstractMountElement::Mount(info) }
 }
 
+impl From<PreservedMount> for AbstractMountElement {
+    fn from(entry: PreservedMount) -> Self { AbstractMountElement::Preserved(entry) }
+}
+
+/// A mount entry which was parsed from a mount tab, and which retains the original line.
+///
+/// Until the entry is modified, it is displayed as the original line, byte for byte. Once it
+/// has been modified, it is rendered from its fields, followed by the trailing comment of the
+/// original line, if it had one.
+///
+/// The line terminator of the original line is kept too, and is written after the entry when
+/// it is displayed as part of a `MountTab`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PreservedMount {
+    info:       MountInfo,
+    original:   MountInfo,
+    line:       String,
+    comment:    Option<String>,
+    terminator: String,
+}
+
+impl PreservedMount {
+    /// Parse a line of a mount tab.
+    ///
+    /// A trailing `\n` or `\r\n` is kept as the terminator of the line, which is otherwise
+    /// `\n`.
+    pub fn new(line: String) -> io::Result<Self> {
+        let (line, terminator) = split_terminator(&line);
+        let terminator = if terminator.is_empty() { "\n" } else { terminator };
+        Self::with_terminator(line.to_owned(), terminator)
+    }
+
+    fn with_terminator(line: String, terminator: &str) -> io::Result<Self> {
+        let comment = line
+            .char_indices()
+            .find(|&(index, c)| {
+                c == '#' && line[..index].ends_with(|c: char| c.is_ascii_whitespace())
+            })
+            .map(|(index, _)| line[index..].to_owned());
+
+        let fields = comment.as_ref().map_or(line.as_str(), |c| &line[..line.len() - c.len()]);
+        let info = fields.parse::<MountInfo>()?;
+
+        Ok(Self { original: info.clone(), info, line, comment, terminator: terminator.to_owned() })
+    }
+
+    /// The line that this entry was parsed from, without its terminator.
+    pub fn line(&self) -> &str { &self.line }
+
+    /// The line terminator of the original line, which is empty if it was the last line of a
+    /// mount tab that did not end with a newline.
+    pub fn terminator(&self) -> &str { &self.terminator }
+
+    /// The trailing comment of the original line, including the `#`.
+    pub fn comment(&self) -> Option<&str> { self.comment.as_deref() }
+
+    /// Returns true if the entry differs from what was parsed from the original line.
+    pub fn is_modified(&self) -> bool { self.info != self.original }
+
+    /// Discard the original line, returning the mount entry.
+    pub fn into_inner(self) -> MountInfo { self.info }
+}
+
+impl Deref for PreservedMount {
+    type Target = MountInfo;
+
+    fn deref(&self) -> &Self::Target { &self.info }
+}
+
+impl DerefMut for PreservedMount {
+    fn deref_mut(&mut self) -> &mut Self::Target { &mut self.info }
+}
+
+impl Display for PreservedMount {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        if !self.is_modified() {
+            return fmt.write_str(&self.line);
+        }
+
+        write!(fmt, "{}", self.info)?;
+        if let Some(ref comment) = self.comment {
+            write!(fmt, " {}", comment)?;
+        }
+
+        Ok(())
+    }
+}
+
 /// Provides an abstract representation of the contents of a mount tab.
 ///
 /// The use case for this type is to enable editing of the original file, or creating new copies,
 /// in a type-safe manner. Each element is an individual line from the original file. Elements
 /// may be inserted, removed, and replaced.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Clone, Default, Debug, Eq, PartialEq)]
 pub struct MountTab(pub Vec<AbstractMountElement>);
 
 impl MountTab {
-    pub fn iter_mounts(&self) -> impl Iterator<Item = &MountInfo> {
-        self.0.iter().filter_map(|e| {
-            if let AbstractMountElement::Mount(e) = e {
-                Some(e)
+    /// Parse a mount tab in a mode which preserves the original text.
+    ///
+    /// Mount entries are stored as `Preserved` elements, and comments and blank lines as
+    /// `Verbatim` elements. Each keeps its line terminator, so that displaying the tab
+    /// reproduces every line that was not modified byte for byte, including `\r\n` line
+    /// endings and a missing newline at the end of the input.
+    pub fn from_str_lossless(input: &str) -> io::Result<Self> {
+        let mut entries = Vec::new();
+
+        for (index, line) in input.split_inclusive('\n').enumerate() {
+            let trimmed = line.trim_start();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                entries.push(AbstractMountElement::Verbatim(line.to_owned()));
             } else {
-                None
+                let (line, terminator) = split_terminator(line);
+                let entry = PreservedMount::with_terminator(line.to_owned(), terminator)
+                    .map_err(|why| error::locate(why, Some(index + 1), None))?;
+                entries.push(AbstractMountElement::Preserved(entry));
             }
-        })
+        }
+
+        Ok(MountTab(entries))
+    }
+
+    pub fn iter_mounts(&self) -> impl Iterator<Item = &MountInfo> {
+        self.0.iter().filter_map(AbstractMountElement::as_mount)
     }
 
     pub fn iter_mounts_mut(&mut self) -> impl Iterator<Item = &mut MountInfo> {
-        self.0.iter_mut().filter_map(|e| {
-            if let AbstractMountElement::Mount(e) = e {
-                Some(e)
-            } else {
-                None
-            }
+        self.0.iter_mut().filter_map(|e| match e {
+            AbstractMountElement::Mount(e) => Some(e),
+            AbstractMountElement::Preserved(e) => Some(&mut e.info),
+            _ => None,
         })
     }
 
@@ -73,6 +209,17 @@ impl MountTab {
     }
 }
 
+/// Split a line into its text and its `\n` or `\r\n` terminator, which is empty if it has none.
+fn split_terminator(line: &str) -> (&str, &'static str) {
+    match line.strip_suffix('\n') {
+        Some(line) => match line.strip_suffix('\r') {
+            Some(line) => (line, "\r\n"),
+            None => (line, "\n"),
+        },
+        None => (line, ""),
+    }
+}
+
 impl Deref for MountTab {
     type Target = Vec<AbstractMountElement>;
 
@@ -86,7 +233,7 @@ impl DerefMut for MountTab {
 impl Display for MountTab {
     fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
         for entry in &self.0 {
-            writeln!(fmt, "{}", entry)?;
+            write!(fmt, "{}{}", entry, entry.terminator())?;
         }
 
         Ok(())
@@ -99,14 +246,16 @@ impl FromStr for MountTab {
     fn from_str(input: &str) -> Result<Self, Self::Err> {
         let mut entries = Vec::new();
 
-        for line in input.lines() {
-            let line = line.trim_start();
-            if line.is_empty() {
+        for (index, line) in input.lines().enumerate() {
+            let trimmed = line.trim_start();
+            if trimmed.is_empty() {
                 entries.push(AbstractMountElement::Empty);
-            } else if line.starts_with('#') {
-                entries.push(AbstractMountElement::Comment(line.to_owned()));
+            } else if trimmed.starts_with('#') {
+                entries.push(AbstractMountElement::Comment(trimmed.to_owned()));
             } else {
-                let info = line.parse::<MountInfo>()?;
+                let info = line
+                    .parse::<MountInfo>()
+                    .map_err(|why| error::locate(why, Some(index + 1), None))?;
                 entries.push(AbstractMountElement::Mount(info));
             }
         }
@@ -114,3 +263,73 @@ impl FromStr for MountTab {
         Ok(MountTab(entries))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    const SAMPLE: &str = r#"# /etc/fstab: static file system information.
+  # indented comment
+
+UUID=1234-ABCD  /boot/efi   vfat    umask=0077      0       1
+/dev/disk/by-uuid/5678    /    ext4   noatime,errors=remount-ro   0   1 # root
+/dev/sda3 /home ext4 defaults
+   
+/swapfile none swap sw 0 0
+"#;
+
+    #[test]
+    fn lossless() {
+        let mut tab = MountTab::from_str_lossless(SAMPLE).unwrap();
+        assert_eq!(tab.to_string(), SAMPLE);
+        assert_eq!(tab.iter_mounts().count(), 4);
+
+        for mount in tab.iter_mounts_mut() {
+            if mount.dest == Path::new("/") {
+                mount.options.push("commit=60");
+            }
+        }
+
+        assert_eq!(
+            tab.to_string(),
+            SAMPLE.replace(
+                "/dev/disk/by-uuid/5678    /    ext4   noatime,errors=remount-ro   0   1 # root",
+                "/dev/disk/by-uuid/5678 / ext4 noatime,errors=remount-ro,commit=60 0 1 # root"
+            )
+        );
+
+        if let AbstractMountElement::Preserved(ref entry) = tab[4] {
+            assert!(entry.is_modified());
+            assert_eq!(entry.comment(), Some("# root"));
+        } else {
+            panic!("expected a preserved mount entry");
+        }
+
+        let input = "# crlf\r\n\r\ntmpfs /tmp tmpfs defaults 0 0\r\n/dev/sda1 / ext4 rw 0 1";
+        let mut tab = MountTab::from_str_lossless(input).unwrap();
+        assert_eq!(tab.len(), 4);
+        assert_eq!(tab[1], AbstractMountElement::Verbatim("\r\n".into()));
+        assert_eq!(tab.to_string(), input);
+
+        tab.iter_mounts_mut().for_each(|mount| mount.pass = 2);
+        assert_eq!(
+            tab.to_string(),
+            "# crlf\r\n\r\ntmpfs /tmp tmpfs defaults 0 2\r\n/dev/sda1 / ext4 rw 0 2"
+        );
+
+        let tab = MountTab::from_str_lossless("tmpfs /tmp tmpfs defaults 0 0\n# end").unwrap();
+        assert_eq!(tab.to_string(), "tmpfs /tmp tmpfs defaults 0 0\n# end");
+
+        let entry = PreservedMount::new("tmpfs /tmp tmpfs defaults 0 0\r\n".into()).unwrap();
+        assert_eq!((entry.line(), entry.terminator()), ("tmpfs /tmp tmpfs defaults 0 0", "\r\n"));
+        let tab = MountTab(vec![
+            entry.into(),
+            PreservedMount::new("/dev/sdb1 /mnt ext4 rw 0 0".into()).unwrap().into(),
+        ]);
+        assert_eq!(
+            tab.to_string(),
+            "tmpfs /tmp tmpfs defaults 0 0\r\n/dev/sdb1 /mnt ext4 rw 0 0\n"
+        );
+    }
+}
@@ -0,0 +1,609 @@
+use super::{MountFlag, MountInfo, MountOption, MountSource, MountTab};
+use crate::{escape::Escaped, FilesystemList, FilesystemSupport};
+use std::{
+    fmt::{self, Display, Formatter},
+    path::{Path, PathBuf},
+};
+
+/// Options which are accepted by the file systems of the given types, besides the options
+/// which are accepted by every file system.
+///
+/// Options of file systems which are not listed are not checked.
+const FILESYSTEM_OPTIONS: &[(&[&str], &[&str])] = &[
+    (
+        &["ext2", "ext3", "ext4"],
+        &[
+            "abort",
+            "acl",
+            "auto_da_alloc",
+            "barrier",
+            "block_validity",
+            "bsddf",
+            "bsdgroups",
+            "check",
+            "commit",
+            "data",
+            "data_err",
+            "dax",
+            "debug",
+            "delalloc",
+            "dioread_lock",
+            "dioread_nolock",
+            "discard",
+            "errors",
+            "grpid",
+            "grpjquota",
+            "grpquota",
+            "i_version",
+            "init_itable",
+            "inlinecrypt",
+            "inode_readahead_blks",
+            "jqfmt",
+            "journal_async_commit",
+            "journal_checksum",
+            "journal_dev",
+            "journal_ioprio",
+            "journal_path",
+            "max_batch_time",
+            "max_dir_size_kb",
+            "mb_optimize_scan",
+            "min_batch_time",
+            "minixdf",
+            "noacl",
+            "noauto_da_alloc",
+            "nobarrier",
+            "noblock_validity",
+            "nocheck",
+            "nodelalloc",
+            "nodiscard",
+            "nogrpid",
+            "noinit_itable",
+            "nojournal_checksum",
+            "noload",
+            "nombcache",
+            "noquota",
+            "norecovery",
+            "nouid32",
+            "nouser_xattr",
+            "oldalloc",
+            "orlov",
+            "prjquota",
+            "quota",
+            "resgid",
+            "resuid",
+            "sb",
+            "stripe",
+            "sysvgroups",
+            "test_dummy_encryption",
+            "user_xattr",
+            "usrjquota",
+            "usrquota",
+        ],
+    ),
+    (
+        &["xfs"],
+        &[
+            "allocsize",
+            "attr2",
+            "bsdgroups",
+            "dax",
+            "discard",
+            "filestreams",
+            "gqnoenforce",
+            "gquota",
+            "grpid",
+            "grpquota",
+            "ikeep",
+            "inode32",
+            "inode64",
+            "largeio",
+            "logbsize",
+            "logbufs",
+            "logdev",
+            "noalign",
+            "noattr2",
+            "nodiscard",
+            "nogrpid",
+            "noikeep",
+            "nolargeio",
+            "norecovery",
+            "nouuid",
+            "noquota",
+            "pqnoenforce",
+            "pquota",
+            "prjquota",
+            "quota",
+            "qnoenforce",
+            "rtdev",
+            "sunit",
+            "swalloc",
+            "swidth",
+            "sysvgroups",
+            "uqnoenforce",
+            "uquota",
+            "usrquota",
+            "wsync",
+        ],
+    ),
+    (
+        &["btrfs"],
+        &[
+            "acl",
+            "autodefrag",
+            "barrier",
+            "check_int",
+            "check_int_data",
+            "clear_cache",
+            "commit",
+            "compress",
+            "compress-force",
+            "datacow",
+            "datasum",
+            "degraded",
+            "device",
+            "discard",
+            "enospc_debug",
+            "fatal_errors",
+            "flushoncommit",
+            "fragment",
+            "max_inline",
+            "metadata_ratio",
+            "noacl",
+            "noautodefrag",
+            "nobarrier",
+            "nodatacow",
+            "nodatasum",
+            "nodiscard",
+            "noenospc_debug",
+            "noflushoncommit",
+            "nologreplay",
+            "norecovery",
+            "nospace_cache",
+            "nossd",
+            "nossd_spread",
+            "notreelog",
+            "rescan_uuid_tree",
+            "rescue",
+            "skip_balance",
+            "space_cache",
+            "ssd",
+            "ssd_spread",
+            "subvol",
+            "subvolid",
+            "thread_pool",
+            "treelog",
+            "usebackuproot",
+            "user_subvol_rm_allowed",
+        ],
+    ),
+    (
+        &["vfat", "msdos", "fat"],
+        &[
+            "allow_utime",
+            "check",
+            "codepage",
+            "conv",
+            "debug",
+            "discard",
+            "dmask",
+            "dos1xfloppy",
+            "dots",
+            "dotsOK",
+            "errors",
+            "fat",
+            "flush",
+            "fmask",
+            "gid",
+            "iocharset",
+            "nfs",
+            "nodots",
+            "nonumtail",
+            "posix",
+            "quiet",
+            "rodir",
+            "shortname",
+            "showexec",
+            "sys_immutable",
+            "time_offset",
+            "tz",
+            "uid",
+            "umask",
+            "uni_xlate",
+            "usefree",
+            "utf8",
+        ],
+    ),
+    (
+        &["tmpfs"],
+        &[
+            "gid",
+            "grpquota",
+            "huge",
+            "inode32",
+            "inode64",
+            "mode",
+            "mpol",
+            "noswap",
+            "nr_blocks",
+            "nr_inodes",
+            "quota",
+            "size",
+            "uid",
+            "usrquota",
+        ],
+    ),
+    (&["swap"], &["discard", "pri", "sw"]),
+];
+
+/// Options which are accepted by every file system, besides flags and userspace options.
+const GENERIC_OPTIONS: &[&str] = &[
+    "bind",
+    "context",
+    "defcontext",
+    "encryption",
+    "fscontext",
+    "iversion",
+    "loop",
+    "noiversion",
+    "nosymfollow",
+    "offset",
+    "private",
+    "rbind",
+    "rootcontext",
+    "rprivate",
+    "rshared",
+    "rslave",
+    "runbindable",
+    "shared",
+    "sizelimit",
+    "slave",
+    "unbindable",
+];
+
+/// File systems which are not checked by `fsck(8)`, and so may have a pass number of 0 when
+/// they are the root file system.
+const UNCHECKED_FILESYSTEMS: &[&str] = &["btrfs", "xfs", "zfs"];
+
+/// How severe a `Diagnostic` is.
+#[derive(Debug, Copy, Clone, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Severity {
+    /// The entry is likely to work, but is probably not what was intended.
+    Warning,
+    /// The entry cannot be mounted as it is.
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.write_str(match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// A problem with an entry of a mount tab.
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub enum Lint {
+    /// The mount point is also used by an entry on an earlier line.
+    DuplicateMountpoint { dest: PathBuf, first: usize },
+    /// The mount point is not an absolute path.
+    RelativeMountpoint(PathBuf),
+    /// The mount point does not exist, or is not a directory.
+    MissingMountpoint(PathBuf),
+    /// The source does not refer to a device which exists, such as an unknown `UUID=`.
+    UnresolvedSource(MountSource),
+    /// The file system type is neither supported by the kernel, nor provided by a module.
+    UnknownFilesystem(String),
+    /// Both flags are given, and the later one overrides the earlier one.
+    ConflictingOptions(MountFlag, MountFlag),
+    /// The pass number is not 0, 1, or 2.
+    InvalidPass(i32),
+    /// The root file system is not checked first.
+    RootPass(i32),
+    /// A swap entry which does not have the `sw` option.
+    SwapWithoutSw,
+    /// An option which the file system does not accept.
+    UnknownOption { fstype: String, option: MountOption },
+}
+
+impl Lint {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Lint::RelativeMountpoint(_)
+            | Lint::MissingMountpoint(_)
+            | Lint::UnresolvedSource(_)
+            | Lint::UnknownFilesystem(_)
+            | Lint::InvalidPass(_) => Severity::Error,
+            Lint::DuplicateMountpoint { .. }
+            | Lint::ConflictingOptions(..)
+            | Lint::RootPass(_)
+            | Lint::SwapWithoutSw
+            | Lint::UnknownOption { .. } => Severity::Warning,
+        }
+    }
+}
+
+impl Display for Lint {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        match self {
+            Lint::DuplicateMountpoint { ref dest, first } => write!(
+                fmt,
+                "{} is already the mount point of line {}",
+                Escaped(dest.as_os_str()),
+                first
+            ),
+            Lint::RelativeMountpoint(ref dest) => {
+                write!(fmt, "mount point {} is not an absolute path", Escaped(dest.as_os_str()))
+            }
+            Lint::MissingMountpoint(ref dest) => write!(
+                fmt,
+                "mount point {} does not exist, or is not a directory",
+                Escaped(dest.as_os_str())
+            ),
+            Lint::UnresolvedSource(ref source) => {
+                write!(fmt, "{} does not refer to a device", source)
+            }
+            Lint::UnknownFilesystem(ref fstype) => {
+                write!(fmt, "{} is not a supported file system type", fstype)
+            }
+            Lint::ConflictingOptions(first, second) => {
+                write!(fmt, "{} is overridden by {}", first, second)
+            }
+            Lint::InvalidPass(pass) => {
+                write!(fmt, "pass number {} is not 0, 1, or 2", pass)
+            }
+            Lint::RootPass(pass) => {
+                write!(fmt, "the root file system should have a pass number of 1, not {}", pass)
+            }
+            Lint::SwapWithoutSw => fmt.write_str("swap entry does not have the sw option"),
+            Lint::UnknownOption { ref fstype, ref option } => {
+                write!(fmt, "{} is not an option of {}", option, fstype)
+            }
+        }
+    }
+}
+
+/// A problem with the entry on a line of a mount tab.
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub struct Diagnostic {
+    /// The line of the entry, counting from 1, which is its position within the tab.
+    pub line: usize,
+    pub lint: Lint,
+}
+
+impl Diagnostic {
+    pub fn severity(&self) -> Severity { self.lint.severity() }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        write!(fmt, "line {}: {}: {}", self.line, self.severity(), self.lint)
+    }
+}
+
+/// Checks the entries of a mount tab for mistakes, such as before it is written.
+///
+/// Mount points are checked beneath a root directory, so that the tab of another installation
+/// may be checked before it is booted. Sources are resolved, and file system types are
+/// checked, against the running system.
+#[derive(Debug, Clone)]
+pub struct FstabValidator {
+    root:              PathBuf,
+    check_mountpoints: bool,
+    filesystems:       Option<FilesystemList>,
+    resolve_sources:   bool,
+}
+
+impl Default for FstabValidator {
+    fn default() -> Self { Self::new() }
+}
+
+impl FstabValidator {
+    /// Check mount points beneath `/`, and file system types against `/proc/filesystems`.
+    pub fn new() -> Self {
+        FstabValidator {
+            root:              PathBuf::from("/"),
+            check_mountpoints: true,
+            filesystems:       FilesystemList::new().ok(),
+            resolve_sources:   true,
+        }
+    }
+
+    /// The directory which mount points are relative to, such as the root of an installation
+    /// target.
+    pub fn root<P: Into<PathBuf>>(mut self, root: P) -> Self {
+        self.root = root.into();
+        self
+    }
+
+    /// Whether mount points are checked to exist as directories beneath the root.
+    pub fn check_mountpoints(mut self, check_mountpoints: bool) -> Self {
+        self.check_mountpoints = check_mountpoints;
+        self
+    }
+
+    /// The file system types which are supported, or `None` to not check file system types.
+    ///
+    /// Types which are not in the list are still accepted if a module provides them.
+    pub fn filesystems(mut self, filesystems: Option<FilesystemList>) -> Self {
+        self.filesystems = filesystems;
+        self
+    }
+
+    /// Whether `UUID=`, `LABEL=`, and device sources are checked to refer to a device.
+    pub fn resolve_sources(mut self, resolve_sources: bool) -> Self {
+        self.resolve_sources = resolve_sources;
+        self
+    }
+
+    /// Check every entry of the tab, returning the problems in the order of their lines.
+    pub fn validate(&self, tab: &MountTab) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        let mut mountpoints: Vec<(&Path, usize)> = Vec::new();
+
+        let entries = tab
+            .iter()
+            .enumerate()
+            .filter_map(|(index, element)| element.as_mount().map(|mount| (index + 1, mount)));
+
+        for (line, mount) in entries {
+            let mut lints = self.lints(mount);
+
+            if !is_swap(mount) {
+                match mountpoints.iter().find(|&&(dest, _)| dest == mount.dest) {
+                    Some(&(_, first)) => {
+                        lints.push(Lint::DuplicateMountpoint { dest: mount.dest.clone(), first });
+                    }
+                    None => mountpoints.push((&mount.dest, line)),
+                }
+            }
+
+            diagnostics.extend(lints.into_iter().map(|lint| Diagnostic { line, lint }));
+        }
+
+        diagnostics
+    }
+
+    fn lints(&self, mount: &MountInfo) -> Vec<Lint> {
+        let mut lints = Vec::new();
+
+        if is_swap(mount) {
+            if !mount.options.contains("sw") {
+                lints.push(Lint::SwapWithoutSw);
+            }
+        } else if !mount.dest.is_absolute() {
+            lints.push(Lint::RelativeMountpoint(mount.dest.clone()));
+        } else if self.check_mountpoints {
+            let path = self.root.join(mount.dest.strip_prefix("/").unwrap_or(&mount.dest));
+            if !path.is_dir() && !mount.options.contains("x-mount.mkdir") {
+                lints.push(Lint::MissingMountpoint(mount.dest.clone()));
+            }
+        }
+
+        if self.resolve_sources && !resolves(&mount.source) {
+            lints.push(Lint::UnresolvedSource(mount.source.clone()));
+        }
+
+        if let Some(ref filesystems) = self.filesystems {
+            let unknown = mount.fstype.split(',').filter(|fstype| {
+                !matches!(*fstype, "auto" | "none" | "swap")
+                    && filesystems.support(fstype) == FilesystemSupport::Unsupported
+            });
+
+            lints.extend(unknown.map(|fstype| Lint::UnknownFilesystem(fstype.into())));
+        }
+
+        let flags = mount.options.flags().collect::<Vec<_>>();
+        for (index, &first) in flags.iter().enumerate() {
+            for &second in &flags[index + 1..] {
+                let lint = Lint::ConflictingOptions(first, second);
+                if second.conflicts_with(first) && !lints.contains(&lint) {
+                    lints.push(lint);
+                }
+            }
+        }
+
+        match mount.pass {
+            0..=2 if mount.dest != Path::new("/") => (),
+            1 => (),
+            0 if UNCHECKED_FILESYSTEMS.contains(&mount.fstype.as_str()) => (),
+            0 | 2 => lints.push(Lint::RootPass(mount.pass)),
+            pass => lints.push(Lint::InvalidPass(pass)),
+        }
+
+        let known = FILESYSTEM_OPTIONS.iter().find(|(types, _)| types.contains(&&*mount.fstype));
+        if let Some(&(_, known)) = known {
+            let unknown = mount.options.iter().filter(|option| {
+                !(option.flag().is_some()
+                    || option.is_userspace()
+                    || option.key.starts_with("X-")
+                    || GENERIC_OPTIONS.contains(&option.key.as_str())
+                    || known.contains(&option.key.as_str()))
+            });
+
+            lints.extend(unknown.map(|option| Lint::UnknownOption {
+                fstype: mount.fstype.clone(),
+                option: option.clone(),
+            }));
+        }
+
+        lints
+    }
+}
+
+impl MountTab {
+    /// Check the entries of the tab against the running system, with a `FstabValidator`.
+    pub fn validate(&self) -> Vec<Diagnostic> { FstabValidator::new().validate(self) }
+}
+
+fn is_swap(mount: &MountInfo) -> bool { mount.fstype == "swap" }
+
+/// Sources which do not refer to devices, and paths outside of `/dev`, such as the sources of
+/// bind mounts and loop devices, are not checked.
+fn resolves(source: &MountSource) -> bool {
+    match source {
+        MountSource::Network(_) | MountSource::Pseudo(_) => true,
+        MountSource::Path(ref path) if !path.starts_with("/dev") => true,
+        _ => matches!(source.resolve(), Ok(ref device) if device.exists()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate() {
+        let tab = "\
+# comment
+/dev/null / ext4 rw,noatime,errors=remount-ro 0 2
+UUID=00000000-0000-0000-0000-000000000000 /dev ext4 ro,rw,colour=blue 0 2
+
+/dev/null /proc ext4 defaults 0 7
+/dev/null /proc notafs defaults 0 0
+/dev/null relative tmpfs size=1G 0 0
+/dev/null /nonexistent/mount/point tmpfs size=1G 0 0
+/dev/null /nonexistent/mount/point tmpfs size=1G,x-mount.mkdir 0 0
+/swapfile none swap defaults 0 0
+/dev/null /sys ext4 nosuid,relatime,noatime 0 1
+"
+        .parse::<MountTab>()
+        .unwrap();
+
+        let filesystems = FilesystemList::parse_from(["\text4", "nodev\ttmpfs"].iter().copied());
+        let validator = FstabValidator::new().filesystems(filesystems.ok());
+        let diagnostics = validator
+            .validate(&tab)
+            .into_iter()
+            .map(|diagnostic| diagnostic.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            diagnostics,
+            vec![
+                "line 2: warning: the root file system should have a pass number of 1, not 2",
+                "line 3: error: UUID=00000000-0000-0000-0000-000000000000 does not refer to a \
+                 device",
+                "line 3: warning: ro is overridden by rw",
+                "line 3: warning: colour=blue is not an option of ext4",
+                "line 5: error: pass number 7 is not 0, 1, or 2",
+                "line 6: error: notafs is not a supported file system type",
+                "line 6: warning: /proc is already the mount point of line 5",
+                "line 7: error: mount point relative is not an absolute path",
+                "line 8: error: mount point /nonexistent/mount/point does not exist, or is not a \
+                 directory",
+                "line 9: warning: /nonexistent/mount/point is already the mount point of line 8",
+                "line 10: warning: swap entry does not have the sw option",
+                "line 11: warning: relatime is overridden by noatime",
+            ]
+        );
+
+        assert_eq!(validator.validate(&tab)[0].severity(), Severity::Warning);
+        assert_eq!(validator.validate(&tab)[1].severity(), Severity::Error);
+
+        let validator = FstabValidator::new().check_mountpoints(false).resolve_sources(false);
+        let tab = "/dev/sda1 /nonexistent ext4 defaults 0 1".parse::<MountTab>().unwrap();
+        assert!(validator.filesystems(None).validate(&tab).is_empty());
+    }
+}
@@ -0,0 +1,483 @@
+use crate::{error, parse, MountInfo, MountInfoEntry, MountOptions, MountSource};
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::{Path, PathBuf},
+};
+
+/// The statistics of a mount, from `/proc/self/mountstats`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Default, Clone, Hash, Eq, PartialEq)]
+pub struct MountStats {
+    /// The source which is mounted.
+    pub source:   MountSource,
+    /// Where the source is mounted.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::os_string"))]
+    pub dest:     PathBuf,
+    /// The type of the mounted file system.
+    pub fstype:   String,
+    /// The version of the statistics format, such as `1.1`, if the file system reports any.
+    pub statvers: Option<String>,
+    /// The statistics of an NFS mount.
+    pub nfs:      Option<NfsStats>,
+}
+
+/// The statistics which the NFS client reports for each of its mounts.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Default, Clone, Hash, Eq, PartialEq)]
+pub struct NfsStats {
+    /// The options of the mount, as negotiated with the server.
+    pub options:    MountOptions,
+    /// The number of seconds since the file system was mounted.
+    pub age:        u64,
+    /// Counters of VFS and NFS events, in the order that the kernel writes them, starting with
+    /// `inoderevalidates`, `dentryrevalidates`, and `datainvalidates`.
+    pub events:     Vec<u64>,
+    /// The numbers of bytes which were read and written.
+    pub bytes:      NfsBytes,
+    /// The statistics of the RPC transport, if one is connected.
+    pub transport:  Option<TransportStats>,
+    /// The statistics of each RPC operation.
+    pub operations: Vec<OperationStats>,
+}
+
+/// The byte counters of an NFS mount.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Default, Copy, Clone, Hash, Eq, PartialEq)]
+pub struct NfsBytes {
+    /// Bytes read by applications with `read(2)`.
+    pub normal_read:  u64,
+    /// Bytes written by applications with `write(2)`.
+    pub normal_write: u64,
+    /// Bytes read from files opened with `O_DIRECT`.
+    pub direct_read:  u64,
+    /// Bytes written to files opened with `O_DIRECT`.
+    pub direct_write: u64,
+    /// Bytes read from the server.
+    pub server_read:  u64,
+    /// Bytes written to the server.
+    pub server_write: u64,
+    /// Pages read from the server.
+    pub read_pages:   u64,
+    /// Pages written to the server.
+    pub write_pages:  u64,
+}
+
+/// The statistics of the transport which RPC requests are sent over.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Default, Clone, Hash, Eq, PartialEq)]
+pub struct TransportStats {
+    /// The transport protocol, such as `tcp`, `udp`, or `rdma`.
+    pub protocol: String,
+    /// The counters of the transport, whose meaning depends upon the protocol.
+    pub counters: Vec<u64>,
+}
+
+/// The statistics of an RPC operation, such as `READ` or `GETATTR`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Default, Clone, Hash, Eq, PartialEq)]
+pub struct OperationStats {
+    /// The name of the operation.
+    pub name:           String,
+    /// The number of operations which were requested.
+    pub operations:     u64,
+    /// The number of times that requests were transmitted, including retransmissions.
+    pub transmissions:  u64,
+    /// The number of requests which timed out.
+    pub major_timeouts: u64,
+    /// The number of bytes which were sent, including RPC headers.
+    pub bytes_sent:     u64,
+    /// The number of bytes which were received, including RPC headers.
+    pub bytes_received: u64,
+    /// The total number of milliseconds that requests waited to be transmitted.
+    pub queue_ms:       u64,
+    /// The total number of milliseconds that the server took to reply.
+    pub rtt_ms:         u64,
+    /// The total number of milliseconds from the request being queued until its reply.
+    pub execute_ms:     u64,
+    /// The number of operations which failed, which older kernels do not report.
+    pub errors:         Option<u64>,
+}
+
+impl OperationStats {
+    /// The average round trip time of an operation, in milliseconds.
+    pub fn average_rtt(&self) -> Option<f64> { self.average(self.rtt_ms) }
+
+    /// The average time from an operation being queued until its reply, in milliseconds.
+    pub fn average_execute(&self) -> Option<f64> { self.average(self.execute_ms) }
+
+    fn average(&self, total: u64) -> Option<f64> {
+        if self.operations == 0 {
+            None
+        } else {
+            Some(total as f64 / self.operations as f64)
+        }
+    }
+}
+
+impl MountStats {
+    /// Parse the `device ... mounted on ... with fstype ...` line which starts a block.
+    ///
+    /// Mounts without a source are reported as `no device mounted on ...`, and are given the
+    /// `none` source.
+    pub fn from_bytes(line: &[u8]) -> io::Result<Self> {
+        let line = parse::Line(line);
+        let fields = line.fields().collect::<Vec<_>>();
+        let words = fields.iter().map(|&(_, field)| field).collect::<Vec<_>>();
+
+        let (source, dest, fstype, rest) = match words.as_slice() {
+            [b"device", _, b"mounted", b"on", _, b"with", b"fstype", _, ..] => {
+                (Some(fields[1]), fields[4], fields[7], &fields[8..])
+            }
+            [b"no", b"device", b"mounted", b"on", _, b"with", b"fstype", _, ..] => {
+                (None, fields[4], fields[7], &fields[8..])
+            }
+            _ => {
+                return Err(
+                    line.error("expected device <source> mounted on <dest> with fstype <type>")
+                )
+            }
+        };
+
+        let statvers = match rest {
+            [] => None,
+            [(offset, field)] => match field.strip_prefix(b"statvers=") {
+                Some(version) => {
+                    let version = (offset + field.len() - version.len(), version);
+                    Some(line.utf8(version, "statvers", "statvers is not UTF-8")?.to_owned())
+                }
+                None => {
+                    return Err(line.field_error(
+                        *offset,
+                        "statvers",
+                        "unexpected field after the type",
+                    ))
+                }
+            },
+            [(offset, _), ..] => {
+                return Err(line.field_error(
+                    *offset,
+                    "statvers",
+                    "unexpected field after the type",
+                ))
+            }
+        };
+
+        let source = match source {
+            Some(source) => MountSource::from(line.decode(source, "source")?),
+            None => MountSource::Pseudo("none".into()),
+        };
+
+        Ok(MountStats {
+            source,
+            dest: PathBuf::from(line.decode(dest, "dest")?),
+            fstype: line.utf8(fstype, "type", "type is not UTF-8")?.to_owned(),
+            statvers,
+            nfs: None,
+        })
+    }
+
+    /// Returns true if these are the statistics of the given mount.
+    pub fn is_for(&self, mount: &MountInfo) -> bool {
+        self.source == mount.source && self.dest == mount.dest
+    }
+
+    /// Returns true if these are the statistics of the given mountinfo entry.
+    pub fn is_for_entry(&self, entry: &MountInfoEntry) -> bool {
+        self.source == entry.source && self.dest == entry.dest
+    }
+
+    /// Parse an indented line of the block, which belongs to the statistics of the file system.
+    ///
+    /// `in_operations` is set once the `per-op statistics` line has been read.
+    fn parse_detail(&mut self, line: parse::Line, in_operations: &mut bool) -> io::Result<()> {
+        let numbers = |values: parse::Field, name| {
+            parse::fields_within(values)
+                .map(|value| line.number::<u64>(value, name, "counter is not a number"))
+                .collect::<io::Result<Vec<u64>>>()
+        };
+
+        if !self.fstype.starts_with("nfs") {
+            return Ok(());
+        }
+
+        let (start, detail) = parse::trim_field((0, line.0));
+        let detail = match detail.iter().rposition(|byte| !byte.is_ascii_whitespace()) {
+            Some(end) => &detail[..=end],
+            None => return Ok(()),
+        };
+
+        let nfs = self.nfs.get_or_insert_with(NfsStats::default);
+        if detail == b"per-op statistics" {
+            *in_operations = true;
+            return Ok(());
+        }
+
+        let colon = match detail.iter().position(|&byte| byte == b':') {
+            Some(colon) => colon,
+            None => return Ok(()),
+        };
+
+        let key = (start, &detail[..colon]);
+        let values = (start + colon + 1, &detail[colon + 1..]);
+
+        if *in_operations {
+            let counters = numbers(values, "operation")?;
+            if counters.len() < 8 {
+                return Err(line.field_error(start, "operation", "too few counters for operation"));
+            }
+
+            nfs.operations.push(OperationStats {
+                name:           line.utf8(key, "operation", "operation is not UTF-8")?.to_owned(),
+                operations:     counters[0],
+                transmissions:  counters[1],
+                major_timeouts: counters[2],
+                bytes_sent:     counters[3],
+                bytes_received: counters[4],
+                queue_ms:       counters[5],
+                rtt_ms:         counters[6],
+                execute_ms:     counters[7],
+                errors:         counters.get(8).copied(),
+            });
+
+            return Ok(());
+        }
+
+        match key.1 {
+            b"opts" => {
+                nfs.options =
+                    MountOptions::from(line.utf8(values, "opts", "opts are not UTF-8")?.trim())
+            }
+            b"age" => {
+                nfs.age = line.number(parse::trim_field(values), "age", "age is not a number")?;
+            }
+            b"events" => nfs.events = numbers(values, "events")?,
+            b"bytes" => {
+                let counters = numbers(values, "bytes")?;
+                if counters.len() < 8 {
+                    return Err(line.field_error(start, "bytes", "too few byte counters"));
+                }
+
+                nfs.bytes = NfsBytes {
+                    normal_read:  counters[0],
+                    normal_write: counters[1],
+                    direct_read:  counters[2],
+                    direct_write: counters[3],
+                    server_read:  counters[4],
+                    server_write: counters[5],
+                    read_pages:   counters[6],
+                    write_pages:  counters[7],
+                };
+            }
+            b"xprt" => {
+                let mut fields = parse::fields_within(values);
+                let protocol = line.next(&mut fields, "transport")?;
+                nfs.transport = Some(TransportStats {
+                    protocol: line
+                        .utf8(protocol, "transport", "transport is not UTF-8")?
+                        .to_owned(),
+                    counters: fields
+                        .map(|value| line.number::<u64>(value, "xprt", "counter is not a number"))
+                        .collect::<io::Result<Vec<u64>>>()?,
+                });
+            }
+            _ => (),
+        }
+
+        Ok(())
+    }
+}
+
+/// A list of parsed mount statistics from `/proc/self/mountstats`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Default, Clone, Hash, Eq, PartialEq)]
+pub struct MountStatsList(pub Vec<MountStats>);
+
+impl MountStatsList {
+    /// Read the statistics of the mounts of the current process.
+    pub fn new() -> io::Result<MountStatsList> {
+        Ok(MountStatsList(MountStatsIter::new()?.collect::<io::Result<Vec<MountStats>>>()?))
+    }
+
+    /// Read the statistics of the mounts of the process with the given PID.
+    pub fn new_from_pid(pid: u32) -> io::Result<MountStatsList> {
+        Self::new_from_file(format!("/proc/{}/mountstats", pid))
+    }
+
+    pub fn new_from_file<P: AsRef<Path>>(path: P) -> io::Result<MountStatsList> {
+        Ok(MountStatsList(
+            MountStatsIter::new_from_file(path)?.collect::<io::Result<Vec<MountStats>>>()?,
+        ))
+    }
+
+    pub fn new_from_reader<R: BufRead>(reader: R) -> io::Result<MountStatsList> {
+        Ok(MountStatsList(
+            MountStatsIter::new_from_reader(reader).collect::<io::Result<Vec<MountStats>>>()?,
+        ))
+    }
+
+    /// Find the statistics of the given mount.
+    ///
+    /// If the same source is mounted more than once at the destination, the statistics of the
+    /// mount which is on top are returned.
+    pub fn get_for(&self, mount: &MountInfo) -> Option<&MountStats> {
+        self.0.iter().rev().find(|stats| stats.is_for(mount))
+    }
+
+    /// Find the statistics of the given mountinfo entry.
+    pub fn get_for_entry(&self, entry: &MountInfoEntry) -> Option<&MountStats> {
+        self.0.iter().rev().find(|stats| stats.is_for_entry(entry))
+    }
+
+    /// The statistics of every NFS mount.
+    pub fn nfs(&self) -> impl Iterator<Item = (&MountStats, &NfsStats)> {
+        self.0.iter().filter_map(|stats| stats.nfs.as_ref().map(|nfs| (stats, nfs)))
+    }
+}
+
+/// Iteratively parse the `/proc/self/mountstats` file.
+pub struct MountStatsIter<R> {
+    file:    R,
+    buffer:  Vec<u8>,
+    pending: Option<MountStats>,
+    line:    usize,
+    path:    Option<PathBuf>,
+}
+
+impl MountStatsIter<BufReader<File>> {
+    pub fn new() -> io::Result<Self> { Self::new_from_file("/proc/self/mountstats") }
+
+    pub fn new_from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let mut iter = Self::new_from_reader(BufReader::new(File::open(path)?));
+        iter.path = Some(path.to_path_buf());
+        Ok(iter)
+    }
+}
+
+impl<R: BufRead> MountStatsIter<R> {
+    pub fn new_from_reader(reader: R) -> Self {
+        Self {
+            file:    reader,
+            buffer:  Vec::with_capacity(512),
+            pending: None,
+            line:    0,
+            path:    None,
+        }
+    }
+
+    fn parse_line(&mut self, in_operations: &mut bool) -> io::Result<Option<MountStats>> {
+        let line = parse::Line(&self.buffer);
+        let start = parse::trim_start(line.0);
+        if start.starts_with(b"device ") || start.starts_with(b"no device ") {
+            let stats = MountStats::from_bytes(line.0)?;
+            *in_operations = false;
+            return Ok(self.pending.replace(stats));
+        }
+
+        match self.pending.as_mut() {
+            Some(stats) => stats.parse_detail(line, in_operations).map(|_| None),
+            None => Err(line.error("statistics found before a device")),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for MountStatsIter<R> {
+    type Item = io::Result<MountStats>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut in_operations = false;
+        loop {
+            self.buffer.clear();
+            match self.file.read_until(b'\n', &mut self.buffer) {
+                Ok(0) => return self.pending.take().map(Ok),
+                Ok(_) => {
+                    self.line += 1;
+                    if parse::fields(&self.buffer).next().is_none() {
+                        continue;
+                    }
+
+                    match self.parse_line(&mut in_operations) {
+                        Ok(Some(previous)) => return Some(Ok(previous)),
+                        Ok(None) => (),
+                        Err(why) => {
+                            return Some(Err(error::locate(
+                                why,
+                                Some(self.line),
+                                self.path.as_deref(),
+                            )))
+                        }
+                    }
+                }
+                Err(why) => return Some(Err(why)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "device proc mounted on /proc with fstype proc
+device /dev/sda2 mounted on / with fstype ext4
+device server:/export mounted on /mnt/my\\040nfs with fstype nfs4 statvers=1.1
+\topts:\trw,vers=4.2,rsize=1048576,wsize=1048576,hard,proto=tcp,timeo=600,retrans=2,sec=sys
+\tage:\t3600
+\tcaps:\tcaps=0x3fff7,wtmult=512,dtsize=32768,bsize=0,namlen=255
+\tsec:\tflavor=1,pseudoflavor=1
+\tevents:\t10 20 30 0 5 6 7 0 0 1 0 0 2 0 0 0 0 3 0 0 0 0 0 0 0 0 0
+\tbytes:\t4096 8192 0 0 4096 8192 1 2
+\tRPC iostats version: 1.1  p/v: 100003/4 (nfs)
+\txprt:\ttcp 875 1 1 0 0 120 120 0 130 0 2 14 6
+\tper-op statistics
+\t        NULL: 1 1 0 44 24 0 0 0 0
+\t        READ: 4 4 0 800 4600 2 10 14 1
+\t       WRITE: 0 0 0 0 0 0 0 0
+
+device tmpfs mounted on /tmp with fstype tmpfs
+no device mounted on /mnt/empty with fstype nfs
+";
+
+    #[test]
+    fn mountstats() {
+        let stats = MountStatsList::new_from_reader(SAMPLE.as_bytes()).unwrap();
+        assert_eq!(stats.0.len(), 5);
+        assert_eq!(stats.0[0].fstype, "proc");
+        assert!(stats.0[1].nfs.is_none());
+        assert_eq!(stats.0[3].dest, PathBuf::from("/tmp"));
+        assert_eq!(stats.0[4].source, MountSource::Pseudo("none".into()));
+        assert_eq!(stats.0[4].dest, PathBuf::from("/mnt/empty"));
+        assert_eq!(stats.0[4].fstype, "nfs");
+
+        let (mount, nfs) = stats.nfs().next().unwrap();
+        assert_eq!(mount.source, MountSource::Network("server:/export".into()));
+        assert_eq!(mount.dest, PathBuf::from("/mnt/my nfs"));
+        assert_eq!(mount.statvers.as_deref(), Some("1.1"));
+        assert_eq!(nfs.options.get("vers"), Some("4.2"));
+        assert_eq!(nfs.age, 3600);
+        assert_eq!(nfs.events.len(), 27);
+        assert_eq!(nfs.bytes.server_write, 8192);
+        assert_eq!(nfs.bytes.write_pages, 2);
+
+        let transport = nfs.transport.as_ref().unwrap();
+        assert_eq!(transport.protocol, "tcp");
+        assert_eq!(transport.counters[0], 875);
+
+        assert_eq!(nfs.operations.len(), 3);
+        let read = &nfs.operations[1];
+        assert_eq!(read.name, "READ");
+        assert_eq!(read.bytes_received, 4600);
+        assert_eq!(read.errors, Some(1));
+        assert_eq!(read.average_rtt(), Some(2.5));
+        assert_eq!(read.average_execute(), Some(3.5));
+        assert_eq!(nfs.operations[2].errors, None);
+        assert_eq!(nfs.operations[2].average_rtt(), None);
+
+        let info = "server:/export /mnt/my\\040nfs nfs4 rw 0 0".parse::<MountInfo>().unwrap();
+        assert_eq!(stats.get_for(&info), Some(mount));
+
+        assert!(MountStats::from_bytes(b"device proc mounted at /proc with fstype proc").is_err());
+        assert!(MountStatsList::new_from_reader(&b"\tage:\t1\n"[..]).is_err());
+    }
+}
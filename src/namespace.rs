@@ -0,0 +1,218 @@
+use crate::{error, MountInfoList};
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, BufReader, Error, ErrorKind},
+    os::unix::io::RawFd,
+    path::{Path, PathBuf},
+};
+
+thread_local! {
+    /// The namespaces found by the last call to `MountNamespace::all` on this thread.
+    // Older versions of clippy mistake the `const` initializer for a `const` item.
+    #[allow(clippy::declare_interior_mutable_const)]
+    static SCANNED: RefCell<Vec<MountNamespace>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A mount namespace, along with the processes which were found to be members of it.
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub struct MountNamespace {
+    /// The inode of the namespace, as seen in the `/proc/<pid>/ns/mnt` symlink.
+    pub inode: u64,
+    /// The PIDs of the processes in this namespace, in ascending order.
+    pub pids:  Vec<u32>,
+}
+
+impl MountNamespace {
+    /// List each distinct mount namespace on the system, by grouping processes by the inode of
+    /// their `/proc/<pid>/ns/mnt` link.
+    ///
+    /// Processes which exit during the scan, or which cannot be inspected due to insufficient
+    /// permissions, are skipped.
+    ///
+    /// The result is remembered, so that opening a namespace by its file descriptor only scans
+    /// `/proc` again once the members which were found have all left it.
+    pub fn all() -> io::Result<Vec<MountNamespace>> {
+        let mut namespaces = BTreeMap::<u64, Vec<u32>>::new();
+
+        for entry in fs::read_dir("/proc")? {
+            let entry = entry?;
+            let pid = match entry.file_name().to_str().and_then(|name| name.parse::<u32>().ok()) {
+                Some(pid) => pid,
+                None => continue,
+            };
+
+            match namespace_of_pid(pid) {
+                Ok(inode) => namespaces.entry(inode).or_default().push(pid),
+                Err(why) if skippable(&why) => continue,
+                Err(why) => return Err(why),
+            }
+        }
+
+        let namespaces: Vec<MountNamespace> = namespaces
+            .into_iter()
+            .map(|(inode, mut pids)| {
+                pids.sort_unstable();
+                MountNamespace { inode, pids }
+            })
+            .collect();
+
+        SCANNED.with(|scanned| *scanned.borrow_mut() = namespaces.clone());
+        Ok(namespaces)
+    }
+
+    /// The mount namespace that the process with the given PID is a member of.
+    pub fn of_pid(pid: u32) -> io::Result<MountNamespace> {
+        Ok(MountNamespace { inode: namespace_of_pid(pid)?, pids: vec![pid] })
+    }
+
+    /// Read the mounts of this namespace through the first member which is still alive.
+    pub fn mounts(&self) -> io::Result<MountInfoList> {
+        let (file, path) = self.open_file("mountinfo")?;
+        MountInfoList::new_from_reader(BufReader::new(file))
+            .map_err(|why| error::locate(why, None, Some(&path)))
+    }
+
+    /// Open a `/proc/<pid>` file of the first member which is still in this namespace, and
+    /// return it along with its path.
+    fn open_file(&self, name: &str) -> io::Result<(File, PathBuf)> {
+        let mut last_error = Error::new(ErrorKind::NotFound, "mount namespace has no members");
+        for &pid in &self.pids {
+            match open_pid_file_in_namespace(pid, self.inode, name) {
+                Ok(file) => return Ok(file),
+                Err(why) => last_error = why,
+            }
+        }
+
+        Err(last_error)
+    }
+}
+
+fn skippable(why: &io::Error) -> bool {
+    matches!(why.kind(), ErrorKind::NotFound | ErrorKind::PermissionDenied)
+}
+
+/// Parse the inode from a namespace link, such as `mnt:[4026531841]`.
+fn parse_namespace_link(link: &Path) -> Option<u64> {
+    link.to_str()?.strip_prefix("mnt:[")?.strip_suffix(']')?.parse::<u64>().ok()
+}
+
+fn read_namespace_link<P: AsRef<Path>>(path: P) -> io::Result<u64> {
+    parse_namespace_link(&fs::read_link(path)?)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "not a mount namespace"))
+}
+
+fn namespace_of_pid(pid: u32) -> io::Result<u64> {
+    read_namespace_link(format!("/proc/{}/ns/mnt", pid))
+}
+
+/// Open a file of the process with the given PID, and verify that the process is still in the
+/// given namespace after the file was opened, so that a reused PID is not mistaken for it.
+fn open_pid_file_in_namespace(pid: u32, inode: u64, name: &str) -> io::Result<(File, PathBuf)> {
+    let path = PathBuf::from(format!("/proc/{}/{}", pid, name));
+    let file = File::open(&path)?;
+    if namespace_of_pid(pid)? != inode {
+        return Err(Error::new(ErrorKind::NotFound, "process has left the mount namespace"));
+    }
+
+    Ok((file, path))
+}
+
+/// Parse the PID from the contents of the fdinfo file of a pidfd.
+fn parse_pidfd_info(info: &str) -> io::Result<u32> {
+    let pid = info
+        .lines()
+        .find_map(|line| line.strip_prefix("Pid:"))
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "file descriptor is not a pidfd"))?
+        .trim();
+
+    if pid == "-1" {
+        return Err(Error::new(ErrorKind::NotFound, "process of the pidfd has exited"));
+    }
+
+    pid.parse::<u32>().map_err(|_| Error::new(ErrorKind::InvalidData, "pidfd PID is not a number"))
+}
+
+fn pid_of_pidfd(pidfd: RawFd) -> io::Result<u32> {
+    parse_pidfd_info(&fs::read_to_string(format!("/proc/self/fdinfo/{}", pidfd))?)
+}
+
+/// Open a file of the process referred to by a pidfd, and return it along with its path.
+///
+/// The pidfd is checked again after the file has been opened, to ensure that the PID was not
+/// reused by another process in the meantime.
+pub(crate) fn open_pidfd_file(pidfd: RawFd, name: &str) -> io::Result<(File, PathBuf)> {
+    let pid = pid_of_pidfd(pidfd)?;
+    let path = PathBuf::from(format!("/proc/{}/{}", pid, name));
+    let file = File::open(&path)?;
+    if pid_of_pidfd(pidfd)? != pid {
+        return Err(Error::new(ErrorKind::NotFound, "process of the pidfd has exited"));
+    }
+
+    Ok((file, path))
+}
+
+/// Open a file of a process which is a member of the mount namespace referred to by `fd`, and
+/// return it along with its path.
+///
+/// The members found by the last scan of `/proc` are tried first.
+pub(crate) fn open_namespace_file(fd: RawFd, name: &str) -> io::Result<(File, PathBuf)> {
+    let inode = read_namespace_link(format!("/proc/self/fd/{}", fd))?;
+    let find = |namespaces: &[MountNamespace]| {
+        namespaces.iter().find(|namespace| namespace.inode == inode).cloned()
+    };
+
+    if let Some(namespace) = SCANNED.with(|scanned| find(&scanned.borrow())) {
+        if let Ok(opened) = namespace.open_file(name) {
+            return Ok(opened);
+        }
+    }
+
+    find(&MountNamespace::all()?)
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "mount namespace has no members"))?
+        .open_file(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::io::AsRawFd;
+
+    #[test]
+    fn namespace_link() {
+        assert_eq!(parse_namespace_link(Path::new("mnt:[4026531841]")), Some(4_026_531_841));
+        assert_eq!(parse_namespace_link(Path::new("net:[4026531840]")), None);
+        assert_eq!(parse_namespace_link(Path::new("mnt:[]")), None);
+    }
+
+    fn find_inode(namespaces: &[MountNamespace], inode: u64) -> Option<MountNamespace> {
+        namespaces.iter().find(|namespace| namespace.inode == inode).cloned()
+    }
+
+    #[test]
+    fn namespace_file() {
+        let ns = File::open("/proc/self/ns/mnt").unwrap();
+        let inode = read_namespace_link("/proc/self/ns/mnt").unwrap();
+
+        SCANNED.with(|scanned| scanned.borrow_mut().clear());
+        let (_, path) = open_namespace_file(ns.as_raw_fd(), "mountinfo").unwrap();
+        assert!(path.starts_with("/proc") && path.ends_with("mountinfo"));
+        assert!(SCANNED.with(|scanned| find_inode(&scanned.borrow(), inode)).is_some());
+
+        // Members which have left are skipped in favour of a new scan.
+        let stale = MountNamespace { inode, pids: vec![u32::MAX] };
+        SCANNED.with(|scanned| *scanned.borrow_mut() = vec![stale]);
+        assert!(open_namespace_file(ns.as_raw_fd(), "mountinfo").is_ok());
+        let rescanned = SCANNED.with(|scanned| find_inode(&scanned.borrow(), inode));
+        assert!(!rescanned.unwrap().pids.contains(&u32::MAX));
+    }
+
+    #[test]
+    fn pidfd_info() {
+        let info = "pos:\t0\nflags:\t02000002\nmnt_id:\t15\nino:\t1057\nPid:\t4242\nNSpid:\t4242\n";
+        assert_eq!(parse_pidfd_info(info).unwrap(), 4242);
+        assert_eq!(parse_pidfd_info("Pid:\t-1\n").unwrap_err().kind(), ErrorKind::NotFound);
+        assert_eq!(parse_pidfd_info("pos:\t0\n").unwrap_err().kind(), ErrorKind::InvalidInput);
+    }
+}
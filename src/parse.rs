@@ -0,0 +1,156 @@
+use crate::{error, escape, ParseError};
+use std::{
+    ffi::OsString,
+    io,
+    str::{self, FromStr},
+};
+
+/// A field of a line, along with its offset in bytes from the start of the line.
+pub(crate) type Field<'a> = (usize, &'a [u8]);
+
+/// Split a line into its whitespace-separated fields.
+pub(crate) fn fields(line: &[u8]) -> impl Iterator<Item = Field<'_>> {
+    let mut offset = 0;
+    line.split(|byte| byte.is_ascii_whitespace()).filter_map(move |field| {
+        let start = offset;
+        offset += field.len() + 1;
+        if field.is_empty() {
+            None
+        } else {
+            Some((start, field))
+        }
+    })
+}
+
+/// Split a field into its whitespace-separated fields, whose offsets are kept relative to the
+/// start of the line.
+pub(crate) fn fields_within(field: Field<'_>) -> impl Iterator<Item = Field<'_>> {
+    let (offset, field) = field;
+    fields(field).map(move |(start, field)| (offset + start, field))
+}
+
+/// Strip leading whitespace from a line.
+pub(crate) fn trim_start(line: &[u8]) -> &[u8] {
+    let start = line.iter().position(|byte| !byte.is_ascii_whitespace()).unwrap_or(line.len());
+    &line[start..]
+}
+
+/// Strip leading whitespace from a field, keeping its offset relative to the start of the line.
+pub(crate) fn trim_field(field: Field<'_>) -> Field<'_> {
+    let (offset, field) = field;
+    let trimmed = trim_start(field);
+    (offset + field.len() - trimmed.len(), trimmed)
+}
+
+/// Interpret a field which must be UTF-8, such as a file system type or list of options.
+pub(crate) fn utf8<'a>(field: &'a [u8], why: &'static str) -> io::Result<&'a str> {
+    str::from_utf8(field).map_err(|_| ParseError::new(why).into())
+}
+
+/// Parse a numeric field.
+pub(crate) fn number<F: FromStr>(field: &[u8], why: &'static str) -> io::Result<F> {
+    str::from_utf8(field)
+        .ok()
+        .and_then(|field| field.parse::<F>().ok())
+        .ok_or_else(|| ParseError::new(why).into())
+}
+
+/// Parse each of the given lines, numbering them from 1 in the errors.
+pub(crate) fn lines<'a, T, I: Iterator<Item = &'a str>>(
+    lines: I,
+    parse: fn(&[u8]) -> io::Result<T>,
+) -> io::Result<Vec<T>> {
+    lines
+        .enumerate()
+        .map(|(index, line)| {
+            parse(line.as_bytes()).map_err(|why| error::locate(why, Some(index + 1), None))
+        })
+        .collect()
+}
+
+/// A line that is being parsed, whose errors record the text of the line, and the name and
+/// column of the field which could not be parsed.
+#[derive(Copy, Clone)]
+pub(crate) struct Line<'a>(pub &'a [u8]);
+
+impl<'a> Line<'a> {
+    pub fn fields(self) -> impl Iterator<Item = Field<'a>> { fields(self.0) }
+
+    /// The next field of the line, or an error naming the field which is missing.
+    pub fn next<I: Iterator<Item = Field<'a>>>(
+        self,
+        fields: &mut I,
+        name: &'static str,
+    ) -> io::Result<Field<'a>> {
+        fields.next().ok_or_else(|| self.missing(name))
+    }
+
+    /// An error which concerns the line as a whole.
+    pub fn error<M: Into<String>>(self, message: M) -> io::Error { self.details(message).into() }
+
+    /// An error which concerns the field at the given offset of the line.
+    pub fn field_error<M: Into<String>>(
+        self,
+        offset: usize,
+        name: &'static str,
+        message: M,
+    ) -> io::Error {
+        ParseError { field: Some(name), column: Some(offset + 1), ..self.details(message) }.into()
+    }
+
+    /// An error for a field which is missing from the end of the line.
+    pub fn missing(self, name: &'static str) -> io::Error {
+        ParseError { field: Some(name), ..self.details(format!("missing {}", name)) }.into()
+    }
+
+    /// Attribute an error which was returned while parsing the field at the given offset to
+    /// that field.
+    pub fn within<T>(
+        self,
+        offset: usize,
+        name: &'static str,
+        result: io::Result<T>,
+    ) -> io::Result<T> {
+        result.map_err(|why| match ParseError::from_io(&why) {
+            Some(details) => self.field_error(offset, name, details.message.clone()),
+            None => self.field_error(offset, name, why.to_string()),
+        })
+    }
+
+    /// Interpret a field which must be UTF-8.
+    pub fn utf8(
+        self,
+        (offset, field): Field<'a>,
+        name: &'static str,
+        why: &'static str,
+    ) -> io::Result<&'a str> {
+        self.within(offset, name, utf8(field, why))
+    }
+
+    /// Parse a numeric field.
+    pub fn number<F: FromStr>(
+        self,
+        (offset, field): Field,
+        name: &'static str,
+        why: &'static str,
+    ) -> io::Result<F> {
+        self.within(offset, name, number(field, why))
+    }
+
+    /// Decode the `\NNN` octal escapes within a field.
+    pub fn decode(self, (offset, field): Field, name: &'static str) -> io::Result<OsString> {
+        self.within(offset, name, escape::decode(field))
+    }
+
+    fn details<M: Into<String>>(self, message: M) -> ParseError {
+        let mut text = self.0;
+        while let [rest @ .., b'\n' | b'\r'] = text {
+            text = rest;
+        }
+
+        ParseError {
+            text: Some(String::from_utf8_lossy(text).into_owned()),
+            ..ParseError::new(message)
+        }
+    }
+}
@@ -0,0 +1,234 @@
+//! Implementations of `Serialize` and `Deserialize` for the types whose serialized form is not
+//! derived from their fields.
+
+use crate::{MountFlag, MountOptions, MountSource, OptionalField};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{ffi::OsString, fmt::Display, str::FromStr};
+
+/// Paths and other OS strings are serialized as strings if they are valid UTF-8, and otherwise
+/// as an array of their bytes. Either form is accepted when deserializing.
+///
+/// Formats which are not human-readable, such as bincode, cannot tell the forms apart, so they
+/// are always given the bytes.
+pub(crate) mod os_string {
+    use serde::{
+        de::{self, SeqAccess, Visitor},
+        Deserializer, Serializer,
+    };
+    use std::{
+        ffi::{OsStr, OsString},
+        fmt::{self, Formatter},
+        os::unix::ffi::{OsStrExt, OsStringExt},
+    };
+
+    pub fn serialize<T: AsRef<OsStr>, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let value = value.as_ref();
+        match value.to_str() {
+            Some(value) if serializer.is_human_readable() => serializer.serialize_str(value),
+            _ => serializer.serialize_bytes(value.as_bytes()),
+        }
+    }
+
+    pub fn deserialize<'de, T: From<OsString>, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(OsStringVisitor).map(T::from)
+        } else {
+            deserializer.deserialize_byte_buf(OsStringVisitor).map(T::from)
+        }
+    }
+
+    struct OsStringVisitor;
+
+    impl<'de> Visitor<'de> for OsStringVisitor {
+        type Value = OsString;
+
+        fn expecting(&self, fmt: &mut Formatter) -> fmt::Result {
+            fmt.write_str("a string or an array of bytes")
+        }
+
+        fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> { Ok(value.into()) }
+
+        fn visit_string<E: de::Error>(self, value: String) -> Result<Self::Value, E> {
+            Ok(value.into())
+        }
+
+        fn visit_bytes<E: de::Error>(self, value: &[u8]) -> Result<Self::Value, E> {
+            Ok(OsStr::from_bytes(value).to_owned())
+        }
+
+        fn visit_byte_buf<E: de::Error>(self, value: Vec<u8>) -> Result<Self::Value, E> {
+            Ok(OsString::from_vec(value))
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+            while let Some(byte) = seq.next_element::<u8>()? {
+                bytes.push(byte);
+            }
+
+            Ok(OsString::from_vec(bytes))
+        }
+    }
+}
+
+/// Deserialize a type from the string that its `FromStr` implementation parses.
+fn parse<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: FromStr,
+    T::Err: Display,
+    D: Deserializer<'de>,
+{
+    String::deserialize(deserializer)?.parse().map_err(de::Error::custom)
+}
+
+/// Serialized as the source was specified, such as `/dev/sda1`, `UUID=...`, or `tmpfs`.
+impl Serialize for MountSource {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut spec = OsString::new();
+        let (tag, value) = self.parts();
+        spec.push(tag);
+        spec.push(value);
+        os_string::serialize(&spec, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for MountSource {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        os_string::deserialize::<OsString, _>(deserializer).map(MountSource::from)
+    }
+}
+
+/// Serialized as a comma-separated string, which is empty rather than `defaults` if there are
+/// no options.
+impl Serialize for MountOptions {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.is_empty() {
+            serializer.serialize_str("")
+        } else {
+            serializer.collect_str(self)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for MountOptions {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        parse(deserializer)
+    }
+}
+
+/// Serialized as the name of the option, such as `ro` or `nosuid`.
+impl Serialize for MountFlag {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for MountFlag {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        parse(deserializer)
+    }
+}
+
+/// Serialized as it is written in mountinfo, such as `shared:1` or `unbindable`.
+impl Serialize for OptionalField {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for OptionalField {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        parse(deserializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        AbstractMountElement, MountEvent, MountInfo, MountInfoEntry, MountList, MountTab, SwapInfo,
+    };
+    use serde_json::json;
+
+    #[test]
+    fn mount_info() {
+        let mount = "UUID=0a1b /mnt/my\\040data ext4 rw,noatime 0 2".parse::<MountInfo>().unwrap();
+        let value = serde_json::to_value(&mount).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "source": "UUID=0a1b",
+                "dest": "/mnt/my data",
+                "fstype": "ext4",
+                "options": "rw,noatime",
+                "dump": 0,
+                "pass": 2,
+            })
+        );
+
+        assert_eq!(serde_json::from_value::<MountInfo>(value).unwrap(), mount);
+
+        let tmpfs = "tmpfs /tmp tmpfs defaults 0 0".parse::<MountInfo>().unwrap();
+        let mounts = MountList(vec![mount.clone(), tmpfs]);
+        let json = serde_json::to_string(&mounts).unwrap();
+        assert_eq!(serde_json::from_str::<MountList>(&json).unwrap(), mounts);
+
+        let event = MountEvent::Removed(mount);
+        let json = serde_json::to_string(&event).unwrap();
+        assert_eq!(serde_json::from_str::<MountEvent>(&json).unwrap(), event);
+    }
+
+    #[test]
+    fn mountinfo_entry() {
+        let line = b"50 28 8:17 /\xff /media/usb\\040\xff rw shared:30 - vfat /dev/sdb1 rw";
+        let entry = MountInfoEntry::from_bytes(line).unwrap();
+        let value = serde_json::to_value(&entry).unwrap();
+        assert_eq!(value["root"], json!([b'/', 0xff]));
+        assert_eq!(value["dest"], json!(b"/media/usb \xff".to_vec()));
+        assert_eq!(value["optional_fields"], json!(["shared:30"]));
+        assert_eq!(value["mount_options"], json!("rw"));
+        assert_eq!(serde_json::from_value::<MountInfoEntry>(value).unwrap(), entry);
+
+        let swap = "/swapfile file 8388600 0 -2".parse::<SwapInfo>().unwrap();
+        let json = serde_json::to_string(&swap).unwrap();
+        assert_eq!(
+            json,
+            r#"{"source":"/swapfile","kind":"file","size":8388600,"used":0,"priority":-2}"#
+        );
+        assert_eq!(serde_json::from_str::<SwapInfo>(&json).unwrap(), swap);
+    }
+
+    #[test]
+    fn mount_tab() {
+        let input = "# <file system> <mount point> <type> <options> <dump> <pass>\r\n\nUUID=0a1b  \
+                     /  ext4  errors=remount-ro  0  1\r\n/swapfile none swap sw 0 0";
+        let mut tab = MountTab::from_str_lossless(input).unwrap();
+        tab.push("tmpfs /tmp tmpfs nosuid 0 0".parse::<MountInfo>().unwrap());
+        assert!(matches!(tab.0[2], AbstractMountElement::Preserved(_)));
+
+        let json = serde_json::to_string(&tab).unwrap();
+        let deserialized = serde_json::from_str::<MountTab>(&json).unwrap();
+        assert_eq!(deserialized, tab);
+        assert!(deserialized.to_string().starts_with(input));
+
+        let preserved = match tab.0[2] {
+            AbstractMountElement::Preserved(ref preserved) => preserved.clone(),
+            _ => unreachable!(),
+        };
+
+        let json = serde_json::to_value(&preserved).unwrap();
+        assert_eq!(serde_json::from_value::<crate::PreservedMount>(json).unwrap(), preserved);
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(serde_json::from_str::<MountInfo>(r#"{"source":1}"#).is_err());
+        assert!(serde_json::from_str::<crate::MountFlag>(r#""rw""#).is_ok());
+        assert!(serde_json::from_str::<crate::MountFlag>(r#""size""#).is_err());
+        assert!(serde_json::from_str::<crate::OptionalField>(r#""shared:x""#).is_err());
+    }
+}
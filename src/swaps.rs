@@ -1,25 +1,31 @@
+use crate::{
+    error,
+    escape::{self, Escaped},
+    parse, Change,
+};
 use std::{
-    char,
     ffi::OsString,
     fmt::{self, Display, Formatter},
     fs::File,
-    io::{self, BufRead, BufReader, Error, ErrorKind},
-    os::unix::ffi::OsStringExt,
+    io::{self, BufRead, BufReader},
     path::{Path, PathBuf},
     str::FromStr,
 };
 
 /// A swap entry, which defines an active swap.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
 pub struct SwapInfo {
     /// The path where the swap originates from.
-    pub source: PathBuf,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::os_string"))]
+    pub source:   PathBuf,
     /// The kind of swap, such as `partition` or `file`.
-    pub kind: OsString,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialization::os_string"))]
+    pub kind:     OsString,
     /// The size of the swap partition.
-    pub size: usize,
+    pub size:     usize,
     /// Whether the swap is used or not.
-    pub used: usize,
+    pub used:     usize,
     /// The priority of a swap, which indicates the order of usage.
     pub priority: isize,
 }
@@ -29,8 +35,8 @@ impl Display for SwapInfo {
         write!(
             fmt,
             "{} {} {} {} {}",
-            self.source.display(),
-            self.kind.to_str().ok_or(fmt::Error)?,
+            Escaped(self.source.as_os_str()),
+            Escaped(&self.kind),
             self.size,
             self.used,
             self.priority
@@ -41,79 +47,61 @@ impl Display for SwapInfo {
 impl FromStr for SwapInfo {
     type Err = io::Error;
 
-    fn from_str(line: &str) -> Result<Self, Self::Err> {
-        let mut parts = line.split_whitespace();
+    fn from_str(line: &str) -> Result<Self, Self::Err> { Self::from_bytes(line.as_bytes()) }
+}
 
-        fn parse<F: FromStr>(string: &OsString) -> io::Result<F> {
-            let string = string.to_str().ok_or_else(|| {
-                Error::new(ErrorKind::InvalidData, "/proc/swaps contains non-UTF8 entry")
-            })?;
+impl SwapInfo {
+    // Attempt to parse a `/proc/swaps`-like line.
+    #[deprecated]
+    pub fn parse_line(line: &str) -> io::Result<SwapInfo> { line.parse::<Self>() }
 
-            string.parse::<F>().map_err(|_| {
-                Error::new(ErrorKind::InvalidData, "/proc/swaps contains invalid data")
-            })
-        }
+    /// Parse a `/proc/swaps`-like line, whose source and kind may contain any bytes.
+    pub fn from_bytes(line: &[u8]) -> io::Result<Self> {
+        let line = parse::Line(line);
+        let mut parts = line.fields();
 
-        macro_rules! next_value {
-            ($err:expr) => {{
-                parts
-                    .next()
-                    .ok_or_else(|| Error::new(ErrorKind::Other, $err))
-                    .and_then(|val| Self::parse_value(val))
-            }};
-        }
+        let source = line.next(&mut parts, "source")?;
+        let kind = line.next(&mut parts, "type")?;
+        let size = line.next(&mut parts, "size")?;
+        let used = line.next(&mut parts, "used")?;
+        let priority = line.next(&mut parts, "priority")?;
 
         Ok(SwapInfo {
-            source:   PathBuf::from(next_value!("Missing source")?),
-            kind:     next_value!("Missing kind")?,
-            size:     parse::<usize>(&next_value!("Missing size")?)?,
-            used:     parse::<usize>(&next_value!("Missing used")?)?,
-            priority: parse::<isize>(&next_value!("Missing priority")?)?,
+            source:   PathBuf::from(line.decode(source, "source")?),
+            kind:     line.decode(kind, "type")?,
+            size:     line.number(size, "size", "size is not a number")?,
+            used:     line.number(used, "used", "used is not a number")?,
+            priority: line.number(priority, "priority", "priority is not a number")?,
         })
     }
-}
-
-impl SwapInfo {
-    // Attempt to parse a `/proc/swaps`-like line.
-    #[deprecated]
-    pub fn parse_line(line: &str) -> io::Result<SwapInfo> { line.parse::<Self>() }
 
-    fn parse_value(value: &str) -> io::Result<OsString> {
-        let mut ret = Vec::new();
-
-        let mut bytes = value.bytes();
-        while let Some(b) = bytes.next() {
-            match b {
-                b'\\' => {
-                    let mut code = 0;
-                    for _i in 0..3 {
-                        if let Some(b) = bytes.next() {
-                            code *= 8;
-                            code += u32::from_str_radix(&(b as char).to_string(), 8)
-                                .map_err(|err| Error::new(ErrorKind::Other, err))?;
-                        } else {
-                            return Err(Error::new(ErrorKind::Other, "truncated octal code"));
-                        }
-                    }
-                    ret.push(code as u8);
-                }
-                _ => {
-                    ret.push(b);
-                }
-            }
-        }
+    /// Write the entry as a `/proc/swaps`-like line, without a trailing newline.
+    ///
+    /// Unlike `Display`, bytes which are not valid UTF-8 are written as they are.
+    pub fn write_bytes<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        let mut line = Vec::with_capacity(64);
+        escape::encode(self.source.as_os_str(), &mut line);
+        line.push(b' ');
+        escape::encode(&self.kind, &mut line);
+        line.extend_from_slice(
+            format!(" {} {} {}", self.size, self.used, self.priority).as_bytes(),
+        );
 
-        Ok(OsString::from_vec(ret))
+        writer.write_all(&line)
     }
 }
 
 /// A list of parsed swap entries from `/proc/swaps`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
 pub struct SwapList(pub Vec<SwapInfo>);
 
 impl SwapList {
+    /// Parse swaps from lines which follow the header of `/proc/swaps`.
+    ///
+    /// The lines are numbered in errors from the first line given, rather than the header.
     pub fn parse_from<'a, I: Iterator<Item = &'a str>>(lines: I) -> io::Result<SwapList> {
-        lines.map(SwapInfo::from_str).collect::<io::Result<Vec<SwapInfo>>>().map(SwapList)
+        parse::lines(lines, SwapInfo::from_bytes).map(SwapList)
     }
 
     pub fn new() -> io::Result<SwapList> {
@@ -132,29 +120,80 @@ impl SwapList {
     pub fn get_swapped(&self, path: &Path) -> bool {
         self.0.iter().any(|mount| mount.source == path)
     }
+
+    /// Compare this list with a newer list of swaps, keyed by their source.
+    ///
+    /// The amount of swap which is used is expected to change, so it is not compared.
+    pub fn diff(&self, new: &SwapList) -> SwapDiff {
+        let mut diff = SwapDiff::default();
+
+        for swap in &self.0 {
+            match new.0.iter().find(|new| new.source == swap.source) {
+                Some(new) => {
+                    if (&swap.kind, swap.size, swap.priority) != (&new.kind, new.size, new.priority)
+                    {
+                        diff.changed.push(Change { old: swap.clone(), new: new.clone() });
+                    }
+                }
+                None => diff.removed.push(swap.clone()),
+            }
+        }
+
+        for swap in &new.0 {
+            if !self.get_swapped(&swap.source) {
+                diff.added.push(swap.clone());
+            }
+        }
+
+        diff
+    }
+}
+
+/// The differences between two lists of swaps.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Default, Clone, Hash, Eq, PartialEq)]
+pub struct SwapDiff {
+    /// Swaps which only exist in the new list.
+    pub added:   Vec<SwapInfo>,
+    /// Swaps which only exist in the old list.
+    pub removed: Vec<SwapInfo>,
+    /// Swaps whose type, size, or priority have changed.
+    pub changed: Vec<Change<SwapInfo>>,
+}
+
+impl SwapDiff {
+    /// Returns true if the lists are equivalent.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
 }
 
 /// Iteratively parse the `/proc/swaps` file.
 pub struct SwapIter<R: BufRead> {
     file:   R,
-    buffer: String,
+    buffer: Vec<u8>,
+    line:   usize,
+    path:   Option<PathBuf>,
 }
 
 impl SwapIter<BufReader<File>> {
     pub fn new() -> io::Result<Self> { Self::new_from_file("/proc/swaps") }
 
     pub fn new_from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
-        Self::new_from_reader(BufReader::new(File::open(path)?))
+        let path = path.as_ref();
+        let mut iter = Self::new_from_reader(BufReader::new(File::open(path)?))?;
+        iter.path = Some(path.to_path_buf());
+        Ok(iter)
     }
 }
 
 impl<R: BufRead> SwapIter<R> {
     pub fn new_from_reader(mut reader: R) -> io::Result<Self> {
-        let mut buffer = String::with_capacity(512);
-        reader.read_line(&mut buffer)?;
+        let mut buffer = Vec::with_capacity(512);
+        reader.read_until(b'\n', &mut buffer)?;
         buffer.clear();
 
-        Ok(Self { file: reader, buffer })
+        Ok(Self { file: reader, buffer, line: 1, path: None })
     }
 }
 
@@ -163,9 +202,15 @@ impl<R: BufRead> Iterator for SwapIter<R> {
 
     fn next(&mut self) -> Option<Self::Item> {
         self.buffer.clear();
-        match self.file.read_line(&mut self.buffer) {
+        match self.file.read_until(b'\n', &mut self.buffer) {
             Ok(read) if read == 0 => None,
-            Ok(_) => Some(SwapInfo::from_str(&self.buffer)),
+            Ok(_) => {
+                self.line += 1;
+                Some(
+                    SwapInfo::from_bytes(&self.buffer)
+                        .map_err(|why| error::locate(why, Some(self.line), self.path.as_deref())),
+                )
+            }
             Err(why) => Some(Err(why)),
         }
     }
@@ -196,4 +241,65 @@ mod tests {
         assert!(swaps.get_swapped(Path::new("/dev/sda5")));
         assert!(!swaps.get_swapped(Path::new("/dev/sda1")));
     }
+
+    #[test]
+    fn escaped_round_trip() {
+        let swap = SwapInfo {
+            source:   PathBuf::from("/swap file"),
+            kind:     OsString::from("file"),
+            size:     1024,
+            used:     0,
+            priority: -2,
+        };
+
+        let line = swap.to_string();
+        assert_eq!(line, "/swap\\040file file 1024 0 -2");
+        assert_eq!(line.parse::<SwapInfo>().unwrap(), swap);
+    }
+
+    #[test]
+    fn non_utf8() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let input = b"Filename Type Size Used Priority\n/swap\xff fi\xffle 1024 0 -2\n";
+        let swaps = SwapList::new_from_reader(&input[..]).unwrap();
+        let swap = &swaps.0[0];
+        assert_eq!(swap.source, Path::new(OsStr::from_bytes(b"/swap\xff")));
+        assert_eq!(swap.kind, OsStr::from_bytes(b"fi\xffle"));
+
+        let line = swap.to_string();
+        assert_eq!(line, "/swap\\377 fi\\377le 1024 0 -2");
+        assert_eq!(&line.parse::<SwapInfo>().unwrap(), swap);
+
+        let mut bytes = Vec::new();
+        swap.write_bytes(&mut bytes).unwrap();
+        assert_eq!(bytes, &input[33..input.len() - 1]);
+    }
+
+    #[test]
+    fn diff() {
+        let old = SwapList::parse_from(
+            "/dev/sda5 partition 8388600 0 -2
+             /swapfile file 1024 0 -3"
+                .lines(),
+        )
+        .unwrap();
+
+        let new = SwapList::parse_from(
+            "/dev/sda5 partition 8388600 4096 -2
+             /swapfile file 2048 0 -3
+             /dev/zram0 partition 4096 0 100"
+                .lines(),
+        )
+        .unwrap();
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.added, vec![new.0[2].clone()]);
+        assert!(diff.removed.is_empty());
+        assert_eq!(diff.changed, vec![Change { old: old.0[1].clone(), new: new.0[1].clone() }]);
+
+        let diff = new.diff(&old);
+        assert_eq!(diff.removed, vec![new.0[2].clone()]);
+        assert!(old.diff(&old).is_empty());
+    }
 }
@@ -0,0 +1,252 @@
+use crate::{escape::Escaped, mount::c_string, FilesystemList, MountInfo, MountList};
+use libc::c_ulong;
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    fs, io,
+    mem::MaybeUninit,
+    os::unix::fs::MetadataExt,
+    path::Path,
+};
+
+/// The usage statistics of a mounted file system, as reported by `statvfs(3)`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Default, Copy, Clone, Hash, Eq, PartialEq)]
+pub struct FsUsage {
+    /// The size of a block of the file system, in bytes.
+    pub block_size:       u64,
+    /// The size of the file system, in bytes.
+    pub total:            u64,
+    /// The number of free bytes.
+    pub free:             u64,
+    /// The number of free bytes which are available to unprivileged users.
+    pub available:        u64,
+    /// The number of inodes.
+    pub inodes:           u64,
+    /// The number of free inodes.
+    pub inodes_free:      u64,
+    /// The number of free inodes which are available to unprivileged users.
+    pub inodes_available: u64,
+    /// The `ST_*` flags of the mount.
+    pub flags:            c_ulong,
+}
+
+impl FsUsage {
+    /// Query the usage of the file system which contains the given path.
+    // The widths of the `statvfs` fields differ between architectures.
+    #[allow(clippy::unnecessary_cast)]
+    pub fn new<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = c_string(path.as_ref())?;
+        let mut stat = MaybeUninit::<libc::statvfs>::uninit();
+        if unsafe { libc::statvfs(path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let stat = unsafe { stat.assume_init() };
+        let block_size = stat.f_frsize as u64;
+        Ok(FsUsage {
+            block_size,
+            total: stat.f_blocks as u64 * block_size,
+            free: stat.f_bfree as u64 * block_size,
+            available: stat.f_bavail as u64 * block_size,
+            inodes: stat.f_files as u64,
+            inodes_free: stat.f_ffree as u64,
+            inodes_available: stat.f_favail as u64,
+            flags: stat.f_flag,
+        })
+    }
+
+    /// The number of bytes which are in use.
+    pub fn used(&self) -> u64 { self.total.saturating_sub(self.free) }
+
+    /// The number of inodes which are in use.
+    pub fn inodes_used(&self) -> u64 { self.inodes.saturating_sub(self.inodes_free) }
+
+    /// The percentage of the space that is usable by unprivileged users which is in use, rounded
+    /// up as `df(1)` does.
+    pub fn used_percent(&self) -> Option<u64> {
+        let used = self.used();
+        match used + self.available {
+            0 => None,
+            usable => Some((used as f64 * 100.0 / usable as f64).ceil() as u64),
+        }
+    }
+
+    /// Returns true if the file system is mounted read-only (`ST_RDONLY`).
+    pub fn is_read_only(&self) -> bool { self.flags & libc::ST_RDONLY != 0 }
+
+    /// Returns true if set-user-ID and set-group-ID bits are ignored (`ST_NOSUID`).
+    pub fn is_nosuid(&self) -> bool { self.flags & libc::ST_NOSUID != 0 }
+}
+
+impl MountInfo {
+    /// Query the usage of the file system which is mounted at the destination of this entry.
+    pub fn usage(&self) -> io::Result<FsUsage> { FsUsage::new(&self.dest) }
+}
+
+impl MountList {
+    /// Query the usage of every mount, as `df(1)` does.
+    ///
+    /// Virtual file systems, and file systems without any blocks, are excluded. Mounts which
+    /// cannot be queried, such as those which are inaccessible or disconnected, are skipped.
+    ///
+    /// A file system which is mounted more than once, such as by a bind mount, is only reported
+    /// once, by the device ID of its destination. Of those mounts, the last to be stacked on the
+    /// same destination, or else the one with the shortest destination, is reported.
+    pub fn usage_report(&self) -> UsageReport<'_> {
+        let filesystems = FilesystemList::new().ok();
+        let queried = self
+            .0
+            .iter()
+            .filter(|mount| mount.class_with(filesystems.as_ref()).is_real())
+            .filter_map(|mount| match (mount.usage(), fs::metadata(&mount.dest)) {
+                (Ok(usage), Ok(metadata)) if usage.total != 0 => {
+                    Some((MountUsage { mount, usage }, metadata.dev()))
+                }
+                _ => None,
+            });
+
+        UsageReport(dedup_devices(queried))
+    }
+}
+
+/// Keep one mount of each device, preferring the last to be stacked on the same destination, or
+/// else the one with the shortest destination.
+fn dedup_devices<'a>(
+    entries: impl IntoIterator<Item = (MountUsage<'a>, u64)>,
+) -> Vec<MountUsage<'a>> {
+    let mut devices: HashMap<u64, usize> = HashMap::new();
+    let mut mounts: Vec<MountUsage> = Vec::new();
+
+    for (entry, device) in entries {
+        match devices.get(&device) {
+            Some(&index) => {
+                let (dest, kept) = (&entry.mount.dest, &mounts[index].mount.dest);
+                if dest == kept || dest.as_os_str().len() < kept.as_os_str().len() {
+                    mounts[index] = entry;
+                }
+            }
+            None => {
+                devices.insert(device, mounts.len());
+                mounts.push(entry);
+            }
+        }
+    }
+
+    mounts
+}
+
+/// The usage of a mount within a `UsageReport`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub struct MountUsage<'a> {
+    /// The entry of the mount table which was queried.
+    pub mount: &'a MountInfo,
+    /// The usage of the file system which is mounted there.
+    pub usage: FsUsage,
+}
+
+/// The usage of each real file system, which displays as a `df -k`-style table.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[derive(Debug, Default, Clone, Hash, Eq, PartialEq)]
+pub struct UsageReport<'a>(pub Vec<MountUsage<'a>>);
+
+impl<'a> Display for UsageReport<'a> {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        let sources = self.0.iter().map(|entry| entry.mount.source.to_string()).collect::<Vec<_>>();
+        let width = sources.iter().map(String::len).chain(Some("Filesystem".len())).max();
+        let width = width.unwrap_or_default();
+
+        writeln!(
+            fmt,
+            "{:<width$} {:>12} {:>12} {:>12} {:>4} Mounted on",
+            "Filesystem",
+            "1K-blocks",
+            "Used",
+            "Available",
+            "Use%",
+            width = width
+        )?;
+
+        for (entry, source) in self.0.iter().zip(sources) {
+            let percent = match entry.usage.used_percent() {
+                Some(percent) => format!("{}%", percent),
+                None => "-".into(),
+            };
+
+            writeln!(
+                fmt,
+                "{:<width$} {:>12} {:>12} {:>12} {:>4} {}",
+                source,
+                entry.usage.total / 1024,
+                entry.usage.used() / 1024,
+                entry.usage.available / 1024,
+                percent,
+                Escaped(entry.mount.dest.as_os_str()),
+                width = width
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usage() {
+        let usage = FsUsage {
+            block_size:       4096,
+            total:            1000 * 4096,
+            free:             400 * 4096,
+            available:        300 * 4096,
+            inodes:           100,
+            inodes_free:      60,
+            inodes_available: 60,
+            flags:            libc::ST_RDONLY,
+        };
+
+        assert_eq!(usage.used(), 600 * 4096);
+        assert_eq!(usage.inodes_used(), 40);
+        assert_eq!(usage.used_percent(), Some(67));
+        assert!(usage.is_read_only());
+        assert!(!usage.is_nosuid());
+        assert_eq!(FsUsage::default().used_percent(), None);
+
+        let mount = "/dev/sda1 / ext4 rw 0 0".parse::<MountInfo>().unwrap();
+        let report = UsageReport(vec![MountUsage { mount: &mount, usage }]);
+        assert_eq!(
+            report.to_string(),
+            "Filesystem    1K-blocks         Used    Available Use% Mounted on\n/dev/sda1          4000         \
+             2400         1200  67% /\n"
+        );
+
+        let root = FsUsage::new("/").unwrap();
+        assert!(root.block_size > 0);
+    }
+
+    #[test]
+    fn report_duplicates() {
+        let mounts = MountList::parse_from(
+            "/dev/sda1 /. ext4 rw 0 0
+             /dev/sda1 / ext4 rw 0 0
+             /dev/sda1 / ext4 rw 0 0
+             /dev/sda2 /does/not/exist ext4 rw 0 0"
+                .lines(),
+        )
+        .unwrap();
+
+        let entries = mounts
+            .0
+            .iter()
+            .zip(&[1, 1, 1, 2])
+            .map(|(mount, &device)| (MountUsage { mount, usage: FsUsage::default() }, device));
+
+        let report = dedup_devices(entries);
+        assert_eq!(report.len(), 2);
+        assert!(std::ptr::eq(report[0].mount, &mounts.0[2]));
+        assert!(std::ptr::eq(report[1].mount, &mounts.0[3]));
+    }
+}
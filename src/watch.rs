@@ -0,0 +1,225 @@
+use crate::{Change, MountDiff, MountInfo, MountInfoList};
+use libc::c_int;
+use std::{
+    fs::File,
+    io::{self, BufReader, Seek, SeekFrom},
+    os::unix::io::{AsRawFd, RawFd},
+    path::Path,
+    time::Duration,
+};
+
+/// A change to the mount table, as observed by a `MountWatcher`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub enum MountEvent {
+    /// A file system was mounted.
+    Added(MountInfo),
+    /// A file system was unmounted.
+    Removed(MountInfo),
+    /// The options of a mount were changed.
+    Remounted { old: MountInfo, new: MountInfo },
+    /// A mount was moved to another destination.
+    Moved { old: MountInfo, new: MountInfo },
+}
+
+impl MountEvent {
+    /// The mount which the event refers to, after it was changed if it still exists.
+    pub fn mount(&self) -> &MountInfo {
+        match self {
+            MountEvent::Added(ref mount)
+            | MountEvent::Removed(ref mount)
+            | MountEvent::Remounted { new: ref mount, .. }
+            | MountEvent::Moved { new: ref mount, .. } => mount,
+        }
+    }
+}
+
+/// Watches the mount table of a process for changes.
+///
+/// The kernel signals `POLLPRI` and `POLLERR` on an open `/proc/self/mountinfo` whenever a
+/// file system is mounted, unmounted, or remounted in its mount namespace. Each time that
+/// happens, the table is read again, and compared with the previous one to produce events.
+/// Mounts are compared by their mount IDs, so that a file system which was unmounted and mounted
+/// again between two reads is still reported, as is a pseudo file system which was moved.
+///
+/// The watcher may also be registered with an event loop through its file descriptor, calling
+/// `refresh` whenever it becomes ready.
+pub struct MountWatcher {
+    file:   File,
+    mounts: MountInfoList,
+}
+
+impl MountWatcher {
+    /// Watch the mounts of the current process.
+    pub fn new() -> io::Result<Self> { Self::new_from_file("/proc/self/mountinfo") }
+
+    /// Watch the mounts of the process with the given PID, from `/proc/<pid>/mountinfo`.
+    pub fn new_from_pid(pid: u32) -> io::Result<Self> {
+        Self::new_from_file(format!("/proc/{}/mountinfo", pid))
+    }
+
+    /// Watch the mounts of any mountinfo file under `/proc`.
+    pub fn new_from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let mounts = read_mounts(&file)?;
+        Ok(MountWatcher { file, mounts })
+    }
+
+    /// The mounts as of the last time that the table was read.
+    pub fn mounts(&self) -> &MountInfoList { &self.mounts }
+
+    /// Wait until the mount table changes, or until the timeout expires, and return the changes.
+    ///
+    /// Waits indefinitely if no timeout is given. An empty list is returned if the timeout
+    /// expired, or if the table was changed back before it could be read.
+    pub fn wait(&mut self, timeout: Option<Duration>) -> io::Result<Vec<MountEvent>> {
+        if poll(self.file.as_raw_fd(), timeout)? {
+            self.refresh()
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    /// Read the mount table again without waiting, and return the changes since it was last read.
+    pub fn refresh(&mut self) -> io::Result<Vec<MountEvent>> {
+        let mounts = read_mounts(&self.file)?;
+        let events = self.mounts.diff(&mounts).into();
+        self.mounts = mounts;
+        Ok(events)
+    }
+}
+
+impl AsRawFd for MountWatcher {
+    fn as_raw_fd(&self) -> RawFd { self.file.as_raw_fd() }
+}
+
+/// Waits for each change of the mount table, and yields the events of the change.
+impl Iterator for MountWatcher {
+    type Item = io::Result<Vec<MountEvent>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.wait(None) {
+                Ok(ref events) if events.is_empty() => continue,
+                result => return Some(result),
+            }
+        }
+    }
+}
+
+fn read_mounts(mut file: &File) -> io::Result<MountInfoList> {
+    file.seek(SeekFrom::Start(0))?;
+    MountInfoList::new_from_reader(BufReader::new(file))
+}
+
+/// Returns true if the file was signaled as changed before the timeout expired.
+fn poll(fd: RawFd, timeout: Option<Duration>) -> io::Result<bool> {
+    let events = libc::POLLPRI | libc::POLLERR;
+    let timeout =
+        timeout.map_or(-1, |timeout| timeout.as_millis().min(c_int::MAX as u128) as c_int);
+    let mut pollfd = libc::pollfd { fd, events, revents: 0 };
+
+    loop {
+        match unsafe { libc::poll(&mut pollfd, 1, timeout) } {
+            -1 => {
+                let why = io::Error::last_os_error();
+                if why.kind() != io::ErrorKind::Interrupted {
+                    return Err(why);
+                }
+            }
+            0 => return Ok(false),
+            _ => return Ok(pollfd.revents & events != 0),
+        }
+    }
+}
+
+impl<T: Into<MountInfo>> From<MountDiff<T>> for Vec<MountEvent> {
+    /// Converts the differences into events, in the order of removed, moved, remounted, and added.
+    fn from(diff: MountDiff<T>) -> Self {
+        let moved = diff
+            .moved
+            .into_iter()
+            .map(|Change { old, new }| MountEvent::Moved { old: old.into(), new: new.into() });
+        let remounted = diff
+            .remounted
+            .into_iter()
+            .map(|Change { old, new }| MountEvent::Remounted { old: old.into(), new: new.into() });
+
+        diff.removed
+            .into_iter()
+            .map(|mount| MountEvent::Removed(mount.into()))
+            .chain(moved)
+            .chain(remounted)
+            .chain(diff.added.into_iter().map(|mount| MountEvent::Added(mount.into())))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MountList;
+
+    #[test]
+    fn mount_events() {
+        let old = MountList::parse_from(
+            "/dev/sda1 / ext4 rw,relatime 0 0
+             tmpfs /tmp tmpfs rw 0 0
+             tmpfs /tmp tmpfs rw 0 0
+             /dev/sdb1 /media/usb vfat rw 0 0"
+                .lines(),
+        )
+        .unwrap();
+
+        let new = MountList::parse_from(
+            "/dev/sda1 / ext4 ro,relatime 0 0
+             tmpfs /tmp tmpfs rw 0 0
+             /dev/sdc1 /media/disk ext4 rw 0 0"
+                .lines(),
+        )
+        .unwrap();
+
+        let events = Vec::<MountEvent>::from(old.diff(&new));
+        assert_eq!(
+            events,
+            vec![
+                MountEvent::Removed(old.0[2].clone()),
+                MountEvent::Removed(old.0[3].clone()),
+                MountEvent::Remounted { old: old.0[0].clone(), new: new.0[0].clone() },
+                MountEvent::Added(new.0[2].clone()),
+            ]
+        );
+
+        assert_eq!(events[2].mount().dest, Path::new("/"));
+        assert!(Vec::<MountEvent>::from(new.diff(&new)).is_empty());
+    }
+
+    #[test]
+    fn mount_info_events() {
+        let old = MountInfoList::parse_from(
+            "28 1 8:2 / / rw,noatime shared:1 - ext4 /dev/sda2 rw
+             41 28 0:45 / /run/user/1000 rw,nosuid,nodev - tmpfs tmpfs rw,mode=700
+             42 28 0:46 / /tmp rw - tmpfs tmpfs rw"
+                .lines(),
+        )
+        .unwrap();
+
+        let new = MountInfoList::parse_from(
+            "28 1 8:2 / / rw,noatime shared:1 - ext4 /dev/sda2 rw
+             41 28 0:45 / /run/user/1001 rw,nosuid,nodev - tmpfs tmpfs rw,mode=700
+             43 28 0:47 / /tmp rw - tmpfs tmpfs rw"
+                .lines(),
+        )
+        .unwrap();
+
+        let events = Vec::<MountEvent>::from(old.diff(&new));
+        assert_eq!(
+            events,
+            vec![
+                MountEvent::Removed(old.0[2].clone().into()),
+                MountEvent::Moved { old: old.0[1].clone().into(), new: new.0[1].clone().into() },
+                MountEvent::Added(new.0[2].clone().into()),
+            ]
+        );
+    }
+}